@@ -0,0 +1,26 @@
+#!/bin/sh
+# Compares the throughput benches of the working tree against a git revision.
+#
+# Usage: benches/compare.sh [REVISION] [CRITERION ARGS...]
+#
+# Benches REVISION (default: HEAD) in a temporary worktree and saves the results as criterion
+# baseline `base`, then benches the working tree against it. Criterion prints the change of
+# each benchmark and flags regressions; the reports are in `target/criterion`.
+
+set -eu
+
+base=${1:-HEAD}
+[ $# -gt 0 ] && shift
+
+root=$(git rev-parse --show-toplevel)
+# share the target directory, so both runs write to the same criterion baselines
+CARGO_TARGET_DIR=${CARGO_TARGET_DIR:-$root/target}
+export CARGO_TARGET_DIR
+
+worktree=$(mktemp -d)
+trap 'git -C "$root" worktree remove --force "$worktree"' EXIT
+git -C "$root" worktree add --detach "$worktree" "$base"
+git -C "$worktree" submodule update --init --recursive
+
+(cd "$worktree" && cargo bench --bench throughput -- --save-baseline base "$@")
+cd "$root" && cargo bench --bench throughput -- --baseline base "$@"
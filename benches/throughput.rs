@@ -0,0 +1,229 @@
+//! Throughput benchmarks of the `Stream` and the raw `Stretcher` grain loop.
+//!
+//! Each iteration stretches one second of input audio, and reports it as 1000 elements, so
+//! criterion's throughput in `Kelem/s` is the real-time factor: `250 Kelem/s` processes audio
+//! 250 times faster than real-time.
+//!
+//! Save a baseline before a change, e.g. a Bungee update, and compare against it afterwards:
+//!
+//! ```sh
+//! cargo bench --bench throughput -- --save-baseline main
+//! cargo bench --bench throughput -- --baseline main
+//! ```
+//!
+//! Or let `benches/compare.sh` do both: it benches a git revision (default `HEAD`) in a temporary
+//! worktree, and compares the working tree against it.
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+
+use bungee_rs::{Request, Stream, StreamBuilder, Stretcher, StretcherBuilder};
+
+// -------------------------------------------------------------------------------------------------
+
+/// Number of input frames which get passed to the stream at once.
+const BLOCK_SIZE: usize = 512;
+
+/// Elements per second of input audio.
+const ELEMENTS_PER_SECOND: u64 = 1000;
+
+// -------------------------------------------------------------------------------------------------
+
+/// Stretch settings of a benchmark.
+#[derive(Debug, Clone, Copy)]
+struct Settings {
+    sample_rate: usize,
+    num_channels: usize,
+    speed: f64,
+    pitch: f64,
+    log2_hop_adjust: i32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            sample_rate: 44100,
+            num_channels: 2,
+            speed: 1.0,
+            pitch: 1.0,
+            log2_hop_adjust: 0,
+        }
+    }
+}
+
+/// The benchmarked variations of the default settings: one group of values per parameter.
+fn variations() -> Vec<(&'static str, Vec<(String, Settings)>)> {
+    let default = Settings::default();
+    let group = |values: &[f64], apply: fn(&mut Settings, f64)| {
+        values
+            .iter()
+            .map(|value| {
+                let mut settings = default;
+                apply(&mut settings, *value);
+                (value.to_string(), settings)
+            })
+            .collect::<Vec<_>>()
+    };
+    vec![
+        (
+            "channels",
+            group(&[1.0, 2.0, 8.0], |s, v| s.num_channels = v as usize),
+        ),
+        (
+            "sample_rate",
+            group(&[44100.0, 48000.0, 96000.0], |s, v| {
+                s.sample_rate = v as usize
+            }),
+        ),
+        (
+            "speed",
+            group(&[0.5, 0.75, 1.0, 1.5, 2.0], |s, v| s.speed = v),
+        ),
+        ("pitch", group(&[0.5, 1.0, 1.5, 2.0], |s, v| s.pitch = v)),
+        (
+            "hop_adjust",
+            group(&[-1.0, 0.0, 1.0], |s, v| s.log2_hop_adjust = v as i32),
+        ),
+    ]
+}
+
+/// One second of planar test audio: a sine with some noise.
+fn test_signal(settings: &Settings) -> Vec<Vec<f32>> {
+    let mut seed = 0x9E37_79B9u32;
+    let samples = (0..settings.sample_rate)
+        .map(|i| {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            let noise = seed as f32 / u32::MAX as f32 - 0.5;
+            let t = i as f32 / settings.sample_rate as f32;
+            0.5 * (t * 440.0 * std::f32::consts::TAU).sin() + 0.1 * noise
+        })
+        .collect::<Vec<_>>();
+    vec![samples; settings.num_channels]
+}
+
+// -------------------------------------------------------------------------------------------------
+
+fn stream(settings: &Settings) -> Stream {
+    StreamBuilder::new(settings.sample_rate, settings.num_channels, BLOCK_SIZE)
+        .log2_synthesis_hop_adjust(settings.log2_hop_adjust)
+        .build()
+        .expect("valid stream settings")
+}
+
+fn stretcher(settings: &Settings) -> Stretcher {
+    StretcherBuilder::new(settings.sample_rate, settings.num_channels)
+        .log2_synthesis_hop_adjust(settings.log2_hop_adjust)
+        .build()
+        .expect("valid stretcher settings")
+}
+
+/// Streams the input through the stream in blocks of `BLOCK_SIZE` frames.
+fn process_stream(
+    stream: &mut Stream,
+    input: &[Vec<f32>],
+    output: &mut [Vec<f32>],
+    settings: &Settings,
+) {
+    let mut input_blocks = input
+        .iter()
+        .map(|channel| channel.chunks(BLOCK_SIZE))
+        .collect::<Vec<_>>();
+    let mut block = Vec::with_capacity(input.len());
+    loop {
+        block.clear();
+        block.extend(input_blocks.iter_mut().filter_map(Iterator::next));
+        if block.len() < input.len() {
+            break;
+        }
+        let frame_count = block[0].len();
+        black_box(stream.process_planar(
+            Some(&block),
+            output,
+            frame_count,
+            frame_count as f64 / settings.speed,
+            settings.pitch,
+        ));
+    }
+}
+
+/// Runs the stretcher's grain loop until the entire input has been analysed.
+fn process_grains(
+    stretcher: &mut Stretcher,
+    input: &[Vec<f32>],
+    buffer: &mut [f32],
+    settings: &Settings,
+) {
+    let input_len = input[0].len();
+    let channel_stride = stretcher.max_input_frame_count();
+    let mut request = Request {
+        position: 0.0,
+        speed: settings.speed,
+        pitch: settings.pitch,
+        reset: true,
+    };
+    stretcher.preroll(&mut request);
+    while request.position < input_len as f64 {
+        let chunk = stretcher.specify_grain(&request);
+        let mute = chunk.mute_range(input_len);
+        let begin = (chunk.begin + mute.head as isize) as usize;
+        let end = (chunk.end - mute.tail as isize) as usize;
+        if end > begin {
+            for (channel, samples) in input.iter().enumerate() {
+                buffer[channel * channel_stride + mute.head..][..end - begin]
+                    .copy_from_slice(&samples[begin..end]);
+            }
+        }
+        stretcher.analyse_grain_with_mute(buffer, channel_stride, mute);
+        black_box(stretcher.synthesise_grain().frame_count);
+        stretcher.next(&mut request);
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+fn bench_stream(c: &mut Criterion) {
+    for (parameter, variations) in variations() {
+        let mut group = c.benchmark_group(format!("stream/{parameter}"));
+        group.throughput(Throughput::Elements(ELEMENTS_PER_SECOND));
+        for (value, settings) in variations {
+            let input = test_signal(&settings);
+            let output_len = (BLOCK_SIZE as f64 / settings.speed).ceil() as usize + 1;
+            let mut output = vec![vec![0.0f32; output_len]; settings.num_channels];
+            // a stream can't be reset, so each iteration starts with a fresh one, which is
+            // created outside of the measurement
+            group.bench_function(BenchmarkId::from_parameter(value), |b| {
+                b.iter_batched(
+                    || stream(&settings),
+                    |mut stream| process_stream(&mut stream, &input, &mut output, &settings),
+                    BatchSize::LargeInput,
+                )
+            });
+        }
+        group.finish();
+    }
+}
+
+fn bench_grains(c: &mut Criterion) {
+    for (parameter, variations) in variations() {
+        let mut group = c.benchmark_group(format!("grains/{parameter}"));
+        group.throughput(Throughput::Elements(ELEMENTS_PER_SECOND));
+        for (value, settings) in variations {
+            let input = test_signal(&settings);
+            let mut stretcher = stretcher(&settings);
+            let channel_stride = stretcher.max_input_frame_count();
+            let mut buffer = vec![0.0f32; channel_stride * settings.num_channels];
+            // the stretcher is reused: `process_grains` starts with a reset request, which
+            // clears the state of the previous iteration
+            group.bench_function(BenchmarkId::from_parameter(value), |b| {
+                b.iter(|| process_grains(&mut stretcher, &input, &mut buffer, &settings))
+            });
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(benches, bench_stream, bench_grains);
+criterion_main!(benches);
@@ -9,68 +9,252 @@ fn main() {
     println!("cargo:rerun-if-env-changed=CFLAGS");
     println!("cargo:rerun-if-env-changed=CXXFLAGS");
 
-    println!("cargo:rerun-if-changed=vendor/");
-    println!("cargo:rerun-if-changed=vendor/bungee/");
-    println!("cargo:rerun-if-changed=vendor/bungee/CMakeLists.txt");
+    // a system library is built from unknown sources, so it can't provide the Pro edition
+    if cfg!(all(feature = "pro", feature = "system")) {
+        panic!(
+            "The `pro` and `system` features can't be combined: the `pro` feature builds Bungee \
+            from the Bungee Pro sources in BUNGEE_PRO_DIR, while `system` links a prebuilt \
+            Bungee library. Disable one of them."
+        );
+    }
+
+    // link a Bungee library which is installed on the system
+    #[cfg(feature = "system")]
+    let include_dirs = link_system_bungee();
+    // or build bungee C++ lib with cmake
+    #[cfg(not(feature = "system"))]
+    let include_dirs = build_bungee();
 
-    // build bungee C++ lib with cmake
-    build_bungee();
     // build our C++ wrappers with cpp_build
-    build_wrappers();
+    build_wrappers(&include_dirs);
 }
 
 // -------------------------------------------------------------------------------------------------
 
-fn build_bungee() {
+#[cfg(not(feature = "system"))]
+fn build_bungee() -> Vec<PathBuf> {
+    println!("cargo:rerun-if-changed=vendor/");
+    println!("cargo:rerun-if-changed=vendor/bungee/");
+    println!("cargo:rerun-if-changed=vendor/bungee/CMakeLists.txt");
+
     let out_dir = PathBuf::from(std::env::var("OUT_DIR").unwrap());
+    let cargo_dir = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap());
     let target_triple = std::env::var("TARGET").unwrap();
 
     if target_triple.contains("android") {
         panic!("android builds are not (yet) supported");
     }
 
+    let bungee_dir = if cfg!(feature = "pro") {
+        pro_source_dir()
+    } else {
+        cargo_dir.join("vendor").join("bungee")
+    };
+    if !bungee_dir.join("CMakeLists.txt").exists() {
+        if cfg!(feature = "pro") {
+            panic!(
+                "BUNGEE_PRO_DIR '{}' does not contain the Bungee Pro C++ sources.",
+                bungee_dir.display()
+            );
+        }
+        panic!(
+            "The vendored Bungee C++ sources at '{}' are missing.\n\
+            Check out the git submodules with `git submodule update --init --recursive`, \
+            or enable the `system` feature to link a Bungee library which is installed \
+            on the system (via pkg-config or BUNGEE_LIB_DIR and BUNGEE_INCLUDE_DIR).",
+            bungee_dir.display()
+        );
+    }
+
     // Seems Rust always links against the release version of the MSVC runtime,
     // even in debug builds. So force building cmake release configs here...
     let profile = match std::env::var("PROFILE").unwrap().as_str() {
         "release" => "Release",
         _ => "RelWithDebInfo",
     };
+    let shared = cfg!(feature = "shared");
 
-    let _ = cmake::Config::new("vendor/bungee")
+    let _ = cmake::Config::new(&bungee_dir)
         .profile(profile)
         .define("CMAKE_EXPORT_COMPILE_COMMANDS", "ON")
         .define("CMAKE_MSVC_RUNTIME_LIBRARY", "MultiThreaded")
-        .define("BUNGEE_BUILD_SHARED_LIBRARY", "OFF")
+        .define(
+            "BUNGEE_BUILD_SHARED_LIBRARY",
+            if shared { "ON" } else { "OFF" },
+        )
         .build_target("bungee_library")
         .build();
 
+    // expose the configuration which cmake built Bungee with, see BUILD_PROFILE and BUILD_FLAGS
+    export_cmake_config(&out_dir.join("build"), profile);
+
     // link bungee C++ lib
     let build_dir = format!("{}", out_dir.clone().join("build").display());
     println!("cargo:rustc-link-search=native={build_dir}");
     println!("cargo:rustc-link-search=native={build_dir}/{profile}",);
-    println!("cargo:rustc-link-search=native={build_dir}/submodules/kissfft");
-    println!("cargo:rustc-link-search=native={build_dir}/submodules/kissfft/{profile}",);
+    if shared {
+        let build_dir = out_dir.join("build");
+        let lib_dir = shared_library_dir(&[build_dir.clone(), build_dir.join(profile)], "bungee")
+            .expect("Failed to locate the shared Bungee library in the cmake build directory");
+        println!("cargo:lib_dir={}", lib_dir.display());
+        // kissfft is either linked statically into the shared library, or built as shared
+        // library of its own, depending on the kissfft options of Bungee's CMakeLists
+        let kissfft_dir = build_dir.join("submodules").join("kissfft");
+        if let Some(kissfft_dir) = shared_library_dir(
+            &[kissfft_dir.clone(), kissfft_dir.join(profile)],
+            "kissfft-float",
+        ) {
+            println!("cargo:rustc-link-search=native={}", kissfft_dir.display());
+            println!("cargo:rustc-link-lib=dylib=kissfft-float");
+        }
+        println!("cargo:rustc-link-lib=dylib=bungee");
+    } else {
+        println!("cargo:rustc-link-search=native={build_dir}/submodules/kissfft");
+        println!("cargo:rustc-link-search=native={build_dir}/submodules/kissfft/{profile}",);
+        println!("cargo:rustc-link-lib=static=bungee");
+        println!("cargo:rustc-link-lib=static=kissfft-float");
+    }
 
-    println!("cargo:rustc-link-lib=static=bungee");
-    println!("cargo:rustc-link-lib=static=kissfft-float");
+    vec![
+        bungee_dir.join("bungee"),
+        bungee_dir.join("submodules"),
+        bungee_dir.join("submodules").join("eigen"),
+    ]
+}
+
+/// Reads the build type and C++ flags of the Bungee build from the cmake cache and exposes them
+/// to the crate as `BUNGEE_SYS_BUILD_PROFILE` and `BUNGEE_SYS_BUILD_FLAGS`.
+#[cfg(not(feature = "system"))]
+fn export_cmake_config(build_dir: &std::path::Path, profile: &str) {
+    let cache = std::fs::read_to_string(build_dir.join("CMakeCache.txt"))
+        .expect("Failed to read the CMakeCache.txt of the Bungee build");
+    // entries are stored as `NAME:TYPE=VALUE`
+    let entry = |name: &str| {
+        cache
+            .lines()
+            .filter_map(|line| line.split_once('='))
+            .find(|(key, _)| key.split(':').next() == Some(name))
+            .map_or("", |(_, value)| value.trim())
+    };
+    // multi-config generators, such as Visual Studio, leave the build type empty
+    let build_type = match entry("CMAKE_BUILD_TYPE") {
+        "" => profile,
+        build_type => build_type,
+    };
+    let config_flags = entry(&format!("CMAKE_CXX_FLAGS_{}", build_type.to_uppercase()));
+    let flags = format!("{} {config_flags}", entry("CMAKE_CXX_FLAGS"));
+    println!("cargo:rustc-env=BUNGEE_SYS_BUILD_PROFILE={build_type}");
+    println!("cargo:rustc-env=BUNGEE_SYS_BUILD_FLAGS={}", flags.trim());
+}
+
+/// Returns the user-supplied Bungee Pro source tree, which replaces the vendored Basic sources.
+#[cfg(not(feature = "system"))]
+fn pro_source_dir() -> PathBuf {
+    println!("cargo:rerun-if-env-changed=BUNGEE_PRO_DIR");
+    match std::env::var_os("BUNGEE_PRO_DIR") {
+        Some(dir) => PathBuf::from(dir),
+        None => panic!(
+            "The `pro` feature requires the BUNGEE_PRO_DIR environment variable, which must \
+            point to a Bungee Pro C++ source tree."
+        ),
+    }
+}
+
+/// Returns the first of the given directories which contains a shared library with the given
+/// name, e.g. `libbungee.so`, `libbungee.dylib` or `bungee.dll`.
+#[cfg(not(feature = "system"))]
+fn shared_library_dir(dirs: &[PathBuf], name: &str) -> Option<PathBuf> {
+    let is_shared_library = |file_name: &str| {
+        let file_name = file_name.strip_prefix("lib").unwrap_or(file_name);
+        file_name.strip_prefix(name).is_some_and(|suffix| {
+            suffix == ".dll" || suffix == ".dylib" || suffix.starts_with(".so")
+        })
+    };
+    dirs.iter()
+        .find(|dir| {
+            std::fs::read_dir(dir).is_ok_and(|entries| {
+                entries
+                    .flatten()
+                    .any(|entry| entry.file_name().to_str().is_some_and(is_shared_library))
+            })
+        })
+        .cloned()
 }
 
 // -------------------------------------------------------------------------------------------------
 
-fn build_wrappers() {
+#[cfg(feature = "system")]
+fn link_system_bungee() -> Vec<PathBuf> {
+    println!("cargo:rerun-if-env-changed=BUNGEE_LIB_DIR");
+    println!("cargo:rerun-if-env-changed=BUNGEE_INCLUDE_DIR");
+
+    let lib_dir = std::env::var_os("BUNGEE_LIB_DIR").map(PathBuf::from);
+    let include_dir = std::env::var_os("BUNGEE_INCLUDE_DIR").map(PathBuf::from);
+
+    // explicitly configured, prebuilt library
+    if lib_dir.is_some() || include_dir.is_some() {
+        let (Some(lib_dir), Some(include_dir)) = (lib_dir, include_dir) else {
+            panic!("BUNGEE_LIB_DIR and BUNGEE_INCLUDE_DIR must be set together");
+        };
+        println!("cargo:rustc-link-search=native={}", lib_dir.display());
+        if cfg!(feature = "shared") {
+            println!("cargo:lib_dir={}", lib_dir.display());
+            println!("cargo:rustc-link-lib=dylib=bungee");
+        } else {
+            println!("cargo:rustc-link-lib=static=bungee");
+            println!("cargo:rustc-link-lib=static=kissfft-float");
+        }
+        return with_bungee_subdirs(vec![include_dir]);
+    }
+
+    // installed library
+    match pkg_config::Config::new()
+        .statik(!cfg!(feature = "shared"))
+        .probe("bungee")
+    {
+        Ok(library) => {
+            if let (true, Some(lib_dir)) = (cfg!(feature = "shared"), library.link_paths.first()) {
+                println!("cargo:lib_dir={}", lib_dir.display());
+            }
+            with_bungee_subdirs(library.include_paths)
+        }
+        Err(err) => panic!(
+            "Failed to find a system Bungee library via pkg-config: {err}\n\
+            Set BUNGEE_LIB_DIR and BUNGEE_INCLUDE_DIR to link a prebuilt library instead."
+        ),
+    }
+}
+
+/// Adds the `bungee` subdirectories of the given include directories, where Bungee installs its
+/// headers, so the wrappers can include them as `"Bungee.h"`.
+#[cfg(feature = "system")]
+fn with_bungee_subdirs(include_dirs: Vec<PathBuf>) -> Vec<PathBuf> {
+    let subdirs = include_dirs
+        .iter()
+        .map(|dir| dir.join("bungee"))
+        .filter(|dir| dir.join("Bungee.h").exists())
+        .collect::<Vec<_>>();
+    include_dirs.into_iter().chain(subdirs).collect()
+}
+
+// -------------------------------------------------------------------------------------------------
+
+fn build_wrappers(include_dirs: &[PathBuf]) {
     println!("cargo:rerun-if-changed=src/lib.rs");
+    println!("cargo:rerun-if-changed=src/pro.rs");
     println!("cargo:rerun-if-changed=src/stream.rs");
     println!("cargo:rerun-if-changed=src/stretcher.rs");
 
-    let cargo_dir = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap());
-    let bungee_dir = cargo_dir.clone().join("vendor").join("bungee");
-    let mut config: cpp_build::Config = cc::Build::new()
-        .static_crt(true) // see CMAKE_MSVC_RUNTIME_LIBRARY above
-        .include(bungee_dir.clone().join("bungee"))
-        .include(bungee_dir.clone().join("submodules"))
-        .include(bungee_dir.clone().join("submodules").join("eigen"))
-        .clone()
-        .into();
+    let mut build = cc::Build::new();
+    build.static_crt(true); // see CMAKE_MSVC_RUNTIME_LIBRARY above
+    for include_dir in include_dirs {
+        build.include(include_dir);
+    }
+    if cfg!(feature = "pro") {
+        // instantiate the wrappers in the `pro` module with the Pro edition
+        build.define("BUNGEE_RS_PRO", None);
+    }
+    let mut config: cpp_build::Config = build.into();
     // See https://docs.rs/cpp_build/latest/src/cpp_build/lib.rs.html#639
     // must be here on the cpp_build::Config to avoid an extra 'C++11' flag.
     config.flag_if_supported("-std=c++20").build("src/lib.rs");
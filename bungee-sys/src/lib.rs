@@ -3,6 +3,8 @@
 
 use std::ffi::{c_double, c_float, c_int, c_uchar};
 
+use cpp::cpp;
+
 // -------------------------------------------------------------------------------------------------
 
 /// Opaque handle to a Bungee stretcher implementation instance.
@@ -87,5 +89,43 @@ pub struct SampleRates {
 
 // -------------------------------------------------------------------------------------------------
 
+/// CMake build type of the Bungee library, e.g. "Release". None when linking a system or
+/// prebuilt library, which was not built by this crate.
+pub const BUILD_PROFILE: Option<&str> = option_env!("BUNGEE_SYS_BUILD_PROFILE");
+
+/// C++ compiler flags of the Bungee library build: `CMAKE_CXX_FLAGS` and the build type's
+/// `CMAKE_CXX_FLAGS_<CONFIG>` from the cmake cache. Compile options which Bungee's CMakeLists
+/// adds to its targets are not included. None when linking a system or prebuilt library.
+pub const BUILD_FLAGS: Option<&str> = option_env!("BUNGEE_SYS_BUILD_FLAGS");
+
+/// Whether the linked Bungee library supports instrumentation, see
+/// [`stretcher::enable_instrumentation`]. All Bungee releases which these bindings support
+/// provide it, and a library without it fails to link.
+pub const INSTRUMENTATION: bool = true;
+
+// -------------------------------------------------------------------------------------------------
+
+// Shared by all C++ wrappers, so it must precede the modules which define them.
+cpp! {{
+    #include "Bungee.h"
+    #include "Stream.h"
+
+    using namespace Bungee;
+    #ifdef BUNGEE_RS_PRO
+    using ProEdition = Bungee::Pro;
+    #else
+    // Without the `pro` feature, the Pro bindings are not compiled on the Rust side, but the
+    // shared wrappers still name the Pro edition, so they are instantiated with Basic instead.
+    using ProEdition = Bungee::Basic;
+    #endif
+
+    // Evaluates the statements in `...` with `Edition` aliased to the Pro edition if `pro` is
+    // true, or to the Basic edition otherwise. Kept on one line, as a backslash is no Rust token.
+    #define BUNGEE_RS_WITH_EDITION(pro, ...) ((pro) ? [&] { using Edition = ProEdition; __VA_ARGS__ }() : [&] { using Edition = Bungee::Basic; __VA_ARGS__ }())
+}}
+
 pub mod stream;
 pub mod stretcher;
+
+#[cfg(feature = "pro")]
+pub mod pro;
@@ -0,0 +1,26 @@
+//! FFI bindings to the Bungee Pro edition of the C++ Stretcher and Stream classes.
+//!
+//! These are generated from the same bindings as the [`stretcher`](crate::stretcher) and
+//! [`stream`](crate::stream) modules, but use `Bungee::Pro` instead of `Bungee::Basic`.
+//! Requires the `pro` feature and a Bungee Pro source tree in `BUNGEE_PRO_DIR`.
+
+/// FFI bindings to the Bungee Pro C++ Stretcher class.
+pub mod stretcher {
+    use std::ffi::{c_char, c_double, c_float, c_int, c_uchar};
+
+    use cpp::cpp;
+
+    use crate::{BungeeStretcher, InputChunk, OutputChunk, Request, SampleRates};
+
+    crate::stretcher::stretcher_bindings!(true);
+}
+
+/// FFI bindings to the Bungee Pro C++ Stream class.
+pub mod stream {
+    use std::ffi::{c_double, c_float, c_int};
+
+    use crate::{BungeeStream, BungeeStretcher};
+    use cpp::cpp;
+
+    crate::stream::stream_bindings!(true);
+}
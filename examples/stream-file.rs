@@ -2,7 +2,7 @@ use std::error::Error;
 
 use arg::{parse_args, Args};
 
-use bungee_rs::Stream;
+use bungee_rs::StreamBuilder;
 
 // -------------------------------------------------------------------------------------------------
 
@@ -55,12 +55,10 @@ fn main() -> Result<(), Box<dyn Error>> {
     let mut wav_output_samples: Vec<i16> = Vec::new(); // interleaved
     let mut wav_write = {
         let output_samples = &mut wav_output_samples;
-        move |planar_output: &Vec<Vec<f32>>, frame_offset: usize, frame_count: usize| {
-            for frame in frame_offset..frame_count {
-                for channel in 0..num_channels {
-                    let sample = planar_output[channel][frame];
-                    output_samples.push((sample * 32767.0) as i16);
-                }
+        move |planar_output: &Vec<Vec<f32>>, frame_count: usize| {
+            for frame in 0..frame_count {
+                let samples = planar_output.iter().map(|channel| channel[frame]);
+                output_samples.extend(samples.map(|sample| (sample * 32767.0) as i16));
             }
         }
     };
@@ -69,8 +67,10 @@ fn main() -> Result<(), Box<dyn Error>> {
     let input_block_size: usize = 1024;
     let output_block_size = (input_block_size as f64 / speed).ceil() as usize;
 
-    // Create stretcher stream
-    let mut stream = Stream::new(sample_rate, num_channels, input_block_size)?;
+    // Create stretcher stream, which skips the stretcher's pre-roll
+    let mut stream = StreamBuilder::new(sample_rate, num_channels, input_block_size)
+        .latency_compensation(true)
+        .build()?;
 
     // Prepare temporary stream buffers (planar)
     let mut input_stream_buffer = vec![vec![0.0f32; input_block_size]; num_channels];
@@ -78,7 +78,6 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     // Process
     let mut wav_reader_frames = wav_reader.frames();
-    let mut remaining_latency_frames = None;
 
     loop {
         // read interleaved frames into planar input buffer
@@ -106,16 +105,8 @@ fn main() -> Result<(), Box<dyn Error>> {
                 pitch,
             );
 
-            // fetch stream's latency after the first process call
-            let remaining_frames_to_skip = *remaining_latency_frames
-                .get_or_insert_with(|| (stream.latency() as f64 / speed) as usize);
-
-            // skip empty latency buffers with the first process calls
-            let frames_to_skip = remaining_frames_to_skip.min(processed_frames);
-            remaining_latency_frames.replace(remaining_frames_to_skip - frames_to_skip);
-
             // write result to interleaved sample buffer vector
-            wav_write(&output_stream_buffer, frames_to_skip, processed_frames);
+            wav_write(&output_stream_buffer, processed_frames);
         }
 
         if frames_read < input_block_size {
@@ -124,27 +115,9 @@ fn main() -> Result<(), Box<dyn Error>> {
     }
 
     // flush remaining output samples
-    let mut remaining_input_frames = stream.latency().ceil() as usize;
-    loop {
-        // process with mute input
-        let input_frames = remaining_input_frames.min(input_block_size);
-        let output_frames_f = input_frames as f64 / speed;
-        let processed_frames = stream.process(
-            None,
-            &mut output_stream_buffer,
-            input_frames,
-            output_frames_f,
-            pitch,
-        );
-
-        // write result to interleaved sample buffer vector
-        wav_write(&output_stream_buffer, 0, processed_frames);
-
-        remaining_input_frames = remaining_input_frames.saturating_sub(input_frames);
-        if remaining_input_frames == 0 {
-            break; // End of output file
-        }
-    }
+    let mut tail_buffer = vec![vec![0.0f32; stream.finish_frame_count()]; num_channels];
+    let tail_frames = stream.finish(&mut tail_buffer);
+    wav_write(&tail_buffer, tail_frames);
 
     // Write Wav output file
     wavers::write(
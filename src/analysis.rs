@@ -0,0 +1,670 @@
+//! Onset, tempo and beat detection, which finds candidate warp markers for [`crate::warp`].
+//!
+//! Onsets get detected with spectral flux: the sum of all increases of the log-compressed
+//! magnitude spectrum between successive analysis frames, followed by adaptive peak picking.
+//! Tempo gets estimated from the autocorrelation of the flux, weighted towards common tempos,
+//! and beats by fitting a constant tempo beat grid to the detected onsets.
+//!
+//! ```rust, no_run
+//! use bungee_rs::{analysis::{self, OnsetDetector}, offline};
+//!
+//! fn main() -> Result<(), bungee_rs::Error> {
+//!     let input = vec![vec![0.0f32; 10 * 44100]; 2];
+//!
+//!     let detector = OnsetDetector::new(44100);
+//!     let envelope = detector.onset_envelope(input.as_slice())?;
+//!     let onsets = detector.detect(input.as_slice())?;
+//!     if let Some(tempo) = analysis::estimate_tempo(&envelope, 60.0..=200.0)? {
+//!         // quantize the detected beats to 128 BPM
+//!         let beats = analysis::track_beats(&envelope, &onsets, tempo.bpm)?;
+//!         let warp = beats.warp_map(128.0)?;
+//!         let output = offline::stretch_warped(input, 44100, &warp, 1.0)?;
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use std::ops::RangeInclusive;
+
+use rustfft::{num_complex::Complex, FftPlanner};
+
+use crate::{source::AudioSource, tempo, warp::WarpMap, Error};
+
+// -------------------------------------------------------------------------------------------------
+
+/// Number of frames which get read from the source at once.
+const READ_BLOCK_SIZE: usize = 16384;
+
+/// Amount of log compression applied to the magnitude spectrum: `ln(1 + COMPRESSION * mag)`.
+const COMPRESSION: f32 = 100.0;
+
+/// Tempo at which the tempo estimator's weighting peaks, in BPM.
+const PREFERRED_BPM: f64 = 120.0;
+
+/// Maximum distance of an onset from the beat grid, relative to the beat period, to be
+/// considered a beat.
+const BEAT_TOLERANCE: f64 = 0.15;
+
+// -------------------------------------------------------------------------------------------------
+
+/// A detected onset: the start of a transient in the input audio.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Onset {
+    /// Frame position of the transient in the input audio.
+    pub position: usize,
+    /// Spectral flux of the onset, normalized to the strongest onset in the envelope.
+    pub strength: f64,
+}
+
+// -------------------------------------------------------------------------------------------------
+
+/// Spectral flux of the input audio, sampled every `hop_size` frames.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OnsetEnvelope {
+    values: Vec<f64>,
+    hop_size: usize,
+    sample_rate: usize,
+}
+
+impl OnsetEnvelope {
+    /// Returns the flux values. The nth value belongs to the analysis frame which is centered
+    /// at input frame `n * hop_size`.
+    pub fn values(&self) -> &[f64] {
+        &self.values
+    }
+
+    /// Returns the distance of the analysis frames in input frames.
+    pub fn hop_size(&self) -> usize {
+        self.hop_size
+    }
+
+    /// Returns the sample rate of the analysed audio.
+    pub fn sample_rate(&self) -> usize {
+        self.sample_rate
+    }
+
+    /// Returns the envelope value at the given, possibly fractional, index, interpolating
+    /// linearly between values. Indices outside of the envelope are 0.
+    fn value_at(values: &[f64], index: f64) -> f64 {
+        if index < 0.0 {
+            return 0.0;
+        }
+        let (index, fraction) = (index as usize, index.fract());
+        let value = |index: usize| values.get(index).copied().unwrap_or(0.0);
+        value(index) * (1.0 - fraction) + value(index + 1) * fraction
+    }
+
+    /// Returns the envelope smoothed with a small gaussian kernel, and with its mean removed.
+    fn smoothed(&self) -> Vec<f64> {
+        const KERNEL: [f64; 5] = [0.06, 0.24, 0.4, 0.24, 0.06];
+        let len = self.values.len();
+        let mut smoothed = (0..len)
+            .map(|index| {
+                KERNEL
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(k, weight)| {
+                        (index + k)
+                            .checked_sub(2)
+                            .and_then(|index| self.values.get(index))
+                            .map(|value| value * weight)
+                    })
+                    .sum::<f64>()
+            })
+            .collect::<Vec<_>>();
+        let mean = smoothed.iter().sum::<f64>() / len.max(1) as f64;
+        smoothed
+            .iter_mut()
+            .for_each(|value| *value = (*value - mean).max(0.0));
+        smoothed
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+/// Spectral-flux onset detector.
+///
+/// The default settings detect percussive onsets in music: 2048 frame Hann windowed analysis
+/// frames every 512 frames, a peak threshold of 0.1 and a minimum onset distance of 30 ms.
+#[derive(Debug, Clone)]
+pub struct OnsetDetector {
+    sample_rate: usize,
+    frame_size: usize,
+    hop_size: usize,
+    threshold: f64,
+    min_interval: f64,
+}
+
+impl OnsetDetector {
+    /// Creates a new detector for audio with the given sample rate and default settings.
+    pub fn new(sample_rate: usize) -> Self {
+        Self {
+            sample_rate,
+            frame_size: 2048,
+            hop_size: 512,
+            threshold: 0.1,
+            min_interval: 0.03,
+        }
+    }
+
+    /// Sets the size of the analysis frames, which gets rounded up to the next power of two.
+    pub fn frame_size(mut self, frame_size: usize) -> Self {
+        self.frame_size = frame_size.max(64).next_power_of_two();
+        self
+    }
+
+    /// Sets the distance of the analysis frames. Smaller hops detect onsets more precisely,
+    /// but take longer.
+    pub fn hop_size(mut self, hop_size: usize) -> Self {
+        self.hop_size = hop_size.max(1);
+        self
+    }
+
+    /// Sets how much an onset's normalized flux must exceed the local average flux. Lower
+    /// thresholds detect more, and weaker, onsets.
+    pub fn threshold(mut self, threshold: f64) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Sets the minimum distance of successive onsets in seconds.
+    pub fn min_interval(mut self, seconds: f64) -> Self {
+        self.min_interval = seconds.max(0.0);
+        self
+    }
+
+    /// Computes the spectral flux of the given audio source, mixed down to mono.
+    ///
+    /// # Errors
+    /// Returns an error if the sample rate or the source's channel count is zero.
+    pub fn onset_envelope(&self, source: impl AudioSource) -> Result<OnsetEnvelope, Error> {
+        let samples = self.read_mono(source)?;
+        Ok(self.flux(&samples))
+    }
+
+    /// Detects onsets in the given audio source. Onset positions get refined from the
+    /// analysis frame to the first frame near the onset which reaches half of the transient's
+    /// peak amplitude.
+    ///
+    /// # Errors
+    /// Returns an error if the sample rate or the source's channel count is zero.
+    pub fn detect(&self, source: impl AudioSource) -> Result<Vec<Onset>, Error> {
+        let samples = self.read_mono(source)?;
+        let mut onsets = self.peaks(&self.flux(&samples));
+        for onset in &mut onsets {
+            // the flux peaks when a transient enters the analysis frame's leading half
+            let begin = onset.position.saturating_sub(self.hop_size);
+            let end = (onset.position + self.frame_size / 2 + self.hop_size).min(samples.len());
+            if begin >= end {
+                continue;
+            }
+            let range = &samples[begin..end];
+            let peak = range.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+            if let Some(offset) = range.iter().position(|s| s.abs() >= 0.5 * peak) {
+                onset.position = begin + offset;
+            }
+        }
+        Ok(onsets)
+    }
+
+    /// Computes the spectral flux of the given mono samples.
+    fn flux(&self, samples: &[f32]) -> OnsetEnvelope {
+        let frame_size = self.frame_size;
+        let half_frame = (frame_size / 2) as isize;
+        let window = (0..frame_size)
+            .map(|i| 0.5 - 0.5 * (std::f32::consts::TAU * i as f32 / frame_size as f32).cos())
+            .collect::<Vec<_>>();
+        let fft = FftPlanner::<f32>::new().plan_fft_forward(frame_size);
+        let mut spectrum = vec![Complex::default(); frame_size];
+        let mut scratch = vec![Complex::default(); fft.get_inplace_scratch_len()];
+        let mut magnitudes = vec![0.0f32; frame_size / 2 + 1];
+        let scale = 2.0 / frame_size as f32;
+
+        let frame_count = samples.len().div_ceil(self.hop_size);
+        let mut values = Vec::with_capacity(frame_count);
+        for frame in 0..frame_count {
+            let start = (frame * self.hop_size) as isize - half_frame;
+            for (i, (bin, weight)) in spectrum.iter_mut().zip(&window).enumerate() {
+                let sample = usize::try_from(start + i as isize)
+                    .ok()
+                    .and_then(|index| samples.get(index))
+                    .copied()
+                    .unwrap_or(0.0);
+                *bin = Complex::new(sample * weight, 0.0);
+            }
+            fft.process_with_scratch(&mut spectrum, &mut scratch);
+
+            let mut flux = 0.0;
+            for (magnitude, bin) in magnitudes.iter_mut().zip(&spectrum) {
+                let compressed = (1.0 + COMPRESSION * bin.norm() * scale).ln();
+                flux += (compressed - *magnitude).max(0.0) as f64;
+                *magnitude = compressed;
+            }
+            values.push(flux);
+        }
+
+        OnsetEnvelope {
+            values,
+            hop_size: self.hop_size,
+            sample_rate: self.sample_rate,
+        }
+    }
+
+    /// Picks onsets from the peaks of the given onset envelope, positioned at the centres of
+    /// the peaks' analysis frames.
+    pub fn peaks(&self, envelope: &OnsetEnvelope) -> Vec<Onset> {
+        const MAX_RADIUS: usize = 3;
+        const MEAN_BEFORE: usize = 16;
+        const MEAN_AFTER: usize = 4;
+
+        let values = envelope.values();
+        let max = values.iter().copied().fold(0.0, f64::max);
+        if max <= 0.0 {
+            return Vec::new();
+        }
+        let min_distance = (self.min_interval * envelope.sample_rate() as f64) as usize;
+        let mut onsets = Vec::<Onset>::new();
+        for (index, value) in values.iter().enumerate() {
+            let mut neighbours = index.saturating_sub(MAX_RADIUS)..(index + MAX_RADIUS + 1);
+            let is_max = neighbours.all(|other| match values.get(other) {
+                Some(other_value) if other < index => value > other_value,
+                Some(other_value) => value >= other_value,
+                None => true,
+            });
+            if !is_max {
+                continue;
+            }
+            let window = &values
+                [index.saturating_sub(MEAN_BEFORE)..(index + MEAN_AFTER + 1).min(values.len())];
+            let mean = window.iter().sum::<f64>() / window.len() as f64;
+            if (value - mean) / max < self.threshold {
+                continue;
+            }
+            let position = index * envelope.hop_size();
+            if onsets
+                .last()
+                .is_some_and(|last| position - last.position < min_distance)
+            {
+                continue;
+            }
+            onsets.push(Onset {
+                position,
+                strength: value / max,
+            });
+        }
+        onsets
+    }
+
+    /// Reads the given audio source, mixed down to mono.
+    fn read_mono(&self, mut source: impl AudioSource) -> Result<Vec<f32>, Error> {
+        if self.sample_rate == 0 {
+            return Err(Error::InvalidSampleRate(self.sample_rate));
+        }
+        let num_channels = source.channels();
+        if num_channels == 0 {
+            return Err(Error::InvalidChannelCount(num_channels));
+        }
+        let len = source.len();
+        let mut samples = Vec::with_capacity(len);
+        let mut block = vec![0.0f32; READ_BLOCK_SIZE * num_channels];
+        for begin in (0..len).step_by(READ_BLOCK_SIZE) {
+            let end = (begin + READ_BLOCK_SIZE).min(len);
+            source.read(begin, end, &mut block, READ_BLOCK_SIZE);
+            samples.extend((0..end - begin).map(|frame| {
+                (0..num_channels)
+                    .map(|channel| block[channel * READ_BLOCK_SIZE + frame])
+                    .sum::<f32>()
+                    / num_channels as f32
+            }));
+        }
+        Ok(samples)
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+/// An estimated tempo.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tempo {
+    /// Tempo in beats per minute.
+    pub bpm: f64,
+    /// Normalized autocorrelation of the onset envelope at the beat period, in range `[0, 1]`.
+    /// Strongly periodic audio, like a click track, has a confidence close to 1.
+    pub confidence: f64,
+}
+
+/// Estimates the tempo of the audio in the given onset envelope, within the given BPM range.
+///
+/// Evaluates the autocorrelation of the onset envelope at the first four multiples of the beat
+/// period for all tempos within the range in 1 BPM steps, and then in 0.1 BPM steps around the
+/// best match. Correlations get weighted with a log-gaussian around 120 BPM to prefer common
+/// tempos over their multiples. Returns None if the audio has no onsets, or if it's shorter
+/// than two beats at the slowest tempo.
+///
+/// # Errors
+/// Returns an error if the BPM range is empty or not positive and finite.
+pub fn estimate_tempo(
+    envelope: &OnsetEnvelope,
+    bpm_range: RangeInclusive<f64>,
+) -> Result<Option<Tempo>, Error> {
+    const COARSE_STEP: f64 = 1.0;
+    const FINE_STEP: f64 = 0.1;
+    // correlating multiple beat periods reduces the envelope hop's quantization error
+    const MULTIPLES: usize = 4;
+
+    let (min_bpm, max_bpm) = bpm_range.into_inner();
+    tempo::validate_bpm(min_bpm)?;
+    tempo::validate_bpm(max_bpm)?;
+    if min_bpm > max_bpm {
+        return Err(Error::InvalidTempo(max_bpm));
+    }
+
+    let values = envelope.smoothed();
+    let frames_per_minute = 60.0 * envelope.sample_rate() as f64 / envelope.hop_size() as f64;
+    let energy = values.iter().map(|v| v * v).sum::<f64>();
+    if energy <= 0.0 || (values.len() as f64) < 2.0 * frames_per_minute / min_bpm {
+        return Ok(None);
+    }
+    let autocorrelation = |lag: f64| {
+        values
+            .iter()
+            .enumerate()
+            .map(|(index, value)| value * OnsetEnvelope::value_at(&values, index as f64 + lag))
+            .sum::<f64>()
+            / energy
+    };
+
+    let evaluate = |bpm: f64| {
+        let period = frames_per_minute / bpm;
+        let correlation = (1..=MULTIPLES)
+            .map(|multiple| autocorrelation(multiple as f64 * period))
+            .sum::<f64>()
+            / MULTIPLES as f64;
+        let weight = (-0.5 * (bpm / PREFERRED_BPM).log2().powi(2)).exp();
+        let tempo = Tempo {
+            bpm,
+            confidence: correlation.clamp(0.0, 1.0),
+        };
+        (correlation * weight, tempo)
+    };
+    let search = |min_bpm: f64, max_bpm: f64, step: f64| {
+        let steps = ((max_bpm - min_bpm) / step).round() as usize;
+        (0..=steps)
+            .map(|index| evaluate((min_bpm + index as f64 * step).min(max_bpm)))
+            .max_by(|(a, _), (b, _)| a.total_cmp(b))
+            .map(|(_, tempo)| tempo)
+    };
+
+    // coarse search in whole BPM steps, then refine around the best match
+    Ok(search(min_bpm, max_bpm, COARSE_STEP).and_then(|coarse| {
+        search(
+            (coarse.bpm - COARSE_STEP).max(min_bpm),
+            (coarse.bpm + COARSE_STEP).min(max_bpm),
+            FINE_STEP,
+        )
+    }))
+}
+
+// -------------------------------------------------------------------------------------------------
+
+/// Beat positions of a constant tempo beat grid, snapped to the detected onsets.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Beats {
+    /// Tempo of the beat grid in BPM.
+    pub bpm: f64,
+    /// Frame positions of the beats in the input audio, in strictly ascending order.
+    pub positions: Vec<f64>,
+    /// Sample rate of the analysed audio.
+    pub sample_rate: usize,
+}
+
+impl Beats {
+    /// Returns a warp map which places the beats on a grid with the given tempo, starting at
+    /// the first beat's position scaled by the tempo change. Rendering the audio with the
+    /// warp map quantizes it to the target tempo.
+    ///
+    /// # Errors
+    /// Returns an error if the tempo is not positive and finite.
+    pub fn warp_map(&self, target_bpm: f64) -> Result<WarpMap, Error> {
+        let speed = tempo::speed_for_bpm(self.bpm, target_bpm)?;
+        let period = 60.0 * self.sample_rate as f64 / target_bpm;
+        let mut warp = WarpMap::new();
+        if let Some(first) = self.positions.first() {
+            let start = first / speed;
+            for (index, position) in self.positions.iter().enumerate() {
+                warp.insert(*position, start + index as f64 * period)?;
+            }
+        }
+        Ok(warp)
+    }
+}
+
+/// Fits a beat grid with the given tempo to the detected onsets.
+///
+/// The grid's phase is chosen so that the most onset strength lies on the grid. Beats which
+/// are close to an onset get snapped to it, and the tempo gets refined with a least squares
+/// fit of the snapped beats. Beats without an onset nearby get interpolated between their
+/// neighbouring snapped beats, or continue with the refined tempo before the first and after
+/// the last snapped beat, so beat positions are strictly increasing. The grid covers the
+/// entire onset envelope.
+///
+/// # Errors
+/// Returns an error if the tempo is not positive and finite.
+pub fn track_beats(envelope: &OnsetEnvelope, onsets: &[Onset], bpm: f64) -> Result<Beats, Error> {
+    tempo::validate_bpm(bpm)?;
+    let sample_rate = envelope.sample_rate();
+    let len = (envelope.values().len() * envelope.hop_size()) as f64;
+    let period = 60.0 * sample_rate as f64 / bpm;
+    let tolerance = BEAT_TOLERANCE * period;
+
+    // distance of a position to the nearest beat of a grid with the given phase
+    let grid_distance = |position: f64, phase: f64| {
+        let offset = (position - phase).rem_euclid(period);
+        offset.min(period - offset)
+    };
+    let phase = onsets
+        .iter()
+        .map(|onset| onset.position as f64 % period)
+        .max_by(|a, b| {
+            let score = |phase: f64| {
+                onsets
+                    .iter()
+                    .filter(|onset| grid_distance(onset.position as f64, phase) < tolerance)
+                    .map(|onset| onset.strength)
+                    .sum::<f64>()
+            };
+            score(*a).total_cmp(&score(*b))
+        })
+        .unwrap_or(0.0);
+
+    // snap grid positions to nearby onsets
+    let beat_count = ((len - phase) / period).ceil().max(0.0) as usize;
+    let snapped = (0..beat_count)
+        .map(|index| {
+            let position = phase + index as f64 * period;
+            onsets
+                .iter()
+                .map(|onset| onset.position as f64)
+                .filter(|onset| (onset - position).abs() < tolerance)
+                .min_by(|a, b| (a - position).abs().total_cmp(&(b - position).abs()))
+        })
+        .collect::<Vec<_>>();
+
+    // least squares fit of the snapped beats: position = origin + index * period
+    let points = snapped
+        .iter()
+        .enumerate()
+        .filter_map(|(index, position)| position.map(|position| (index as f64, position)))
+        .collect::<Vec<_>>();
+    let (origin, period) = if points.len() >= 2 {
+        let count = points.len() as f64;
+        let mean_index = points.iter().map(|(i, _)| i).sum::<f64>() / count;
+        let mean_position = points.iter().map(|(_, p)| p).sum::<f64>() / count;
+        let covariance = points
+            .iter()
+            .map(|(i, p)| (i - mean_index) * (p - mean_position))
+            .sum::<f64>();
+        let variance = points
+            .iter()
+            .map(|(i, _)| (i - mean_index).powi(2))
+            .sum::<f64>();
+        let fitted_period = covariance / variance;
+        (mean_position - mean_index * fitted_period, fitted_period)
+    } else {
+        (phase, period)
+    };
+
+    // fill in beats without an onset between or beyond the snapped beats, which are strictly
+    // increasing, as adjacent beats can't snap to the same onset
+    let positions = (0..snapped.len())
+        .map(|index| {
+            if let Some(position) = snapped[index] {
+                return position;
+            }
+            let prev = (0..index).rev().find_map(|i| snapped[i].map(|p| (i, p)));
+            let next = (index + 1..snapped.len()).find_map(|i| snapped[i].map(|p| (i, p)));
+            match (prev, next) {
+                (Some((i0, p0)), Some((i1, p1))) => {
+                    p0 + (p1 - p0) * (index - i0) as f64 / (i1 - i0) as f64
+                }
+                (Some((i0, p0)), None) => p0 + (index - i0) as f64 * period,
+                (None, Some((i1, p1))) => p1 - (i1 - index) as f64 * period,
+                (None, None) => origin + index as f64 * period,
+            }
+        })
+        .collect();
+    Ok(Beats {
+        bpm: 60.0 * sample_rate as f64 / period,
+        positions,
+        sample_rate,
+    })
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: usize = 44100;
+
+    /// Start frames of the clicks of a click track at the given tempo, starting at `offset`.
+    fn click_starts(bpm: f64, offset: usize, seconds: usize) -> Vec<usize> {
+        let period = 60.0 * SAMPLE_RATE as f64 / bpm;
+        (0..)
+            .map(|beat| offset + (beat as f64 * period).round() as usize)
+            .take_while(|start| *start < seconds * SAMPLE_RATE)
+            .collect()
+    }
+
+    /// A click track: short decaying 1 kHz bursts at the given tempo, starting at `offset`.
+    fn click_track(bpm: f64, offset: usize, seconds: usize) -> Vec<f32> {
+        clicks(&click_starts(bpm, offset, seconds), seconds)
+    }
+
+    /// Short decaying 1 kHz bursts at the given start frames.
+    fn clicks(starts: &[usize], seconds: usize) -> Vec<f32> {
+        let mut samples = vec![0.0f32; seconds * SAMPLE_RATE];
+        for &start in starts {
+            for (i, sample) in samples[start..].iter_mut().take(441).enumerate() {
+                let t = i as f32 / SAMPLE_RATE as f32;
+                *sample = 0.8 * (-t * 200.0).exp() * (t * 1000.0 * std::f32::consts::TAU).cos();
+            }
+        }
+        samples
+    }
+
+    #[test]
+    fn onsets() {
+        let input = click_track(120.0, 1000, 4);
+        let source: &[&[f32]] = &[&input, &input];
+        let detector = OnsetDetector::new(SAMPLE_RATE);
+        let onsets = detector.detect(source).unwrap();
+        assert_eq!(onsets.len(), 8);
+        for (index, onset) in onsets.iter().enumerate() {
+            let expected = 1000 + index * SAMPLE_RATE / 2;
+            assert!(onset.position.abs_diff(expected) <= 8, "{onset:?}");
+            assert!(onset.strength > 0.5);
+        }
+
+        // silence has no onsets
+        let silence = vec![vec![0.0f32; SAMPLE_RATE]];
+        assert!(detector.detect(silence).unwrap().is_empty());
+
+        assert!(matches!(
+            OnsetDetector::new(0).detect(source),
+            Err(Error::InvalidSampleRate(0))
+        ));
+    }
+
+    #[test]
+    fn tempo_and_beats() {
+        let detector = OnsetDetector::new(SAMPLE_RATE);
+        for bpm in [90.0, 120.0, 128.0, 140.0, 174.0] {
+            let input = vec![click_track(bpm, 5000, 8)];
+            let envelope = detector.onset_envelope(input.as_slice()).unwrap();
+            let tempo = estimate_tempo(&envelope, 60.0..=200.0).unwrap().unwrap();
+            assert!((tempo.bpm - bpm).abs() < 1.0, "{bpm}: {tempo:?}");
+            assert!(tempo.confidence > 0.5, "{bpm}: {tempo:?}");
+
+            let onsets = detector.detect(input.as_slice()).unwrap();
+            let beats = track_beats(&envelope, &onsets, tempo.bpm).unwrap();
+            assert!((beats.bpm - bpm).abs() < 0.05, "{bpm}: {:?}", beats.bpm);
+            let period = 60.0 * SAMPLE_RATE as f64 / bpm;
+            for (index, position) in beats.positions.iter().enumerate() {
+                let expected = 5000.0 + (index as f64 * period).round();
+                assert!((position - expected).abs() <= 8.0, "{bpm}: {position}");
+            }
+
+            // the warp map moves the beats onto a 100 BPM grid
+            let warp = beats.warp_map(100.0).unwrap();
+            assert_eq!(warp.len(), beats.positions.len());
+            let target_period = 60.0 * SAMPLE_RATE as f64 / 100.0;
+            let targets = warp.markers().iter().map(|m| m.target).collect::<Vec<_>>();
+            assert!(targets
+                .windows(2)
+                .all(|t| (t[1] - t[0] - target_period).abs() < 1e-6));
+        }
+
+        // too short or silent audio has no tempo
+        let envelope = detector
+            .onset_envelope(vec![click_track(120.0, 0, 1)])
+            .unwrap();
+        assert_eq!(estimate_tempo(&envelope, 40.0..=200.0).unwrap(), None);
+        let envelope = detector
+            .onset_envelope(vec![vec![0.0f32; 4 * SAMPLE_RATE]])
+            .unwrap();
+        assert_eq!(estimate_tempo(&envelope, 60.0..=200.0).unwrap(), None);
+        assert!(matches!(
+            estimate_tempo(&envelope, 200.0..=60.0),
+            Err(Error::InvalidTempo(_))
+        ));
+    }
+
+    #[test]
+    fn beats_with_missing_and_misplaced_clicks() {
+        const BPM: f64 = 120.0;
+        let period = 60.0 * SAMPLE_RATE as f64 / BPM;
+        let expected = click_starts(BPM, 5000, 8);
+        // drop the 5th click, and move the 10th one off the grid by 40% of a beat
+        let mut starts = expected.clone();
+        starts[9] += (0.4 * period) as usize;
+        starts.remove(4);
+        let input = vec![clicks(&starts, 8)];
+
+        let detector = OnsetDetector::new(SAMPLE_RATE);
+        let envelope = detector.onset_envelope(input.as_slice()).unwrap();
+        let onsets = detector.detect(input.as_slice()).unwrap();
+        let beats = track_beats(&envelope, &onsets, BPM).unwrap();
+        assert!((beats.bpm - BPM).abs() < 0.05, "{:?}", beats.bpm);
+
+        // beats stay on the grid, where they are strictly increasing
+        assert!(beats.positions.windows(2).all(|p| p[0] < p[1]));
+        assert_eq!(beats.positions.len(), expected.len());
+        for (position, expected) in beats.positions.iter().zip(&expected) {
+            assert!((position - *expected as f64).abs() <= 8.0, "{position}");
+        }
+        let warp = beats.warp_map(100.0).unwrap();
+        assert_eq!(warp.len(), beats.positions.len());
+    }
+}
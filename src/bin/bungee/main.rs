@@ -0,0 +1,388 @@
+//! `bungee` command-line tool: time-stretches and pitch-shifts audio files or directories of
+//! audio files with the offline stretcher, and writes the results as WAV files.
+//!
+//! Progress is reported per file: the offline stretcher renders each file as a whole, so a
+//! single, long file shows no progress until it is done.
+//!
+//! Exit codes: 0 on success, 1 when processing a file failed, 2 for invalid arguments.
+
+use std::{
+    collections::HashMap,
+    error::Error,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::Duration,
+};
+
+use clap::{error::ErrorKind, Args, CommandFactory, Parser};
+use indicatif::{ProgressBar, ProgressStyle};
+
+use bungee_rs::{io, offline, tempo};
+
+mod wav;
+use wav::SampleFormat;
+
+// -------------------------------------------------------------------------------------------------
+
+/// Exit code for files which failed to process.
+const EXIT_FAILURE: u8 = 1;
+
+/// File extensions which get processed in batch mode.
+const EXTENSIONS: [&str; 5] = ["wav", "flac", "ogg", "oga", "mp3"];
+
+// -------------------------------------------------------------------------------------------------
+
+/// Time-stretches and pitch-shifts audio files with Bungee.
+///
+/// Reads WAV, FLAC, Ogg Vorbis or MP3 files and writes WAV files. When INPUT is a directory,
+/// all audio files in it and its subdirectories get processed into the OUTPUT directory, keeping
+/// their names but with a .wav extension. Files which would end up with the same output path
+/// are rejected.
+#[derive(Parser, Debug)]
+#[command(name = "bungee", version)]
+struct Arguments {
+    /// Input audio file or directory
+    input: PathBuf,
+    /// Output WAV file or directory
+    output: PathBuf,
+    #[command(flatten)]
+    speed: SpeedArgs,
+    /// Tempo of the input in BPM, required by --bpm
+    #[arg(long, value_name = "BPM", requires = "bpm")]
+    source_bpm: Option<f64>,
+    #[command(flatten)]
+    pitch: PitchArgs,
+    /// Output sample format
+    #[arg(short, long, value_enum, default_value = "16")]
+    format: SampleFormat,
+    /// Disable TPDF dither when writing integer sample formats
+    #[arg(long)]
+    no_dither: bool,
+    /// Overwrite existing output files
+    #[arg(long)]
+    overwrite: bool,
+    /// Don't show progress, which is reported per processed file
+    #[arg(short, long)]
+    quiet: bool,
+}
+
+/// Stretch arguments: one of them may be specified.
+#[derive(Args, Debug)]
+#[group(multiple = false)]
+struct SpeedArgs {
+    /// Speed ratio: 2 plays twice as fast, 0.5 at half speed
+    #[arg(short, long, value_name = "RATIO")]
+    speed: Option<f64>,
+    /// Target duration of the output in seconds
+    #[arg(short, long, value_name = "SECONDS")]
+    duration: Option<f64>,
+    /// Target tempo of the output in BPM
+    #[arg(long, value_name = "BPM", requires = "source_bpm")]
+    bpm: Option<f64>,
+}
+
+/// Pitch arguments: either a ratio, or semitones and cents.
+#[derive(Args, Debug)]
+struct PitchArgs {
+    /// Pitch ratio: 2 shifts one octave up, 0.5 one octave down
+    #[arg(short, long, value_name = "RATIO", conflicts_with_all = ["semitones", "cents"])]
+    pitch: Option<f64>,
+    /// Pitch shift in semitones
+    #[arg(long, allow_negative_numbers = true)]
+    semitones: Option<f64>,
+    /// Pitch shift in cents, added to the semitones
+    #[arg(long, allow_negative_numbers = true)]
+    cents: Option<f64>,
+}
+
+// -------------------------------------------------------------------------------------------------
+
+/// How the stretch speed of a file gets determined.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Speed {
+    Ratio(f64),
+    Duration(f64),
+    Tempo { source_bpm: f64, target_bpm: f64 },
+}
+
+/// Validated processing settings.
+#[derive(Debug, Clone, Copy)]
+struct Settings {
+    speed: Speed,
+    pitch: f64,
+    format: SampleFormat,
+    dither: bool,
+    overwrite: bool,
+}
+
+impl Settings {
+    fn new(args: &Arguments) -> Result<Self, String> {
+        fn positive(name: &str, value: f64) -> Result<f64, String> {
+            if value.is_finite() && value > 0.0 {
+                Ok(value)
+            } else {
+                Err(format!(
+                    "{name} must be a positive number, but is '{value}'"
+                ))
+            }
+        }
+
+        let speed = match (args.speed.speed, args.speed.duration, args.speed.bpm) {
+            (_, Some(seconds), _) => Speed::Duration(positive("--duration", seconds)?),
+            (_, _, Some(bpm)) => {
+                // clap ensures that --bpm is only accepted along with --source-bpm
+                let source_bpm = args.source_bpm.expect("--bpm requires --source-bpm");
+                Speed::Tempo {
+                    source_bpm: positive("--source-bpm", source_bpm)?,
+                    target_bpm: positive("--bpm", bpm)?,
+                }
+            }
+            (ratio, _, _) => Speed::Ratio(positive("--speed", ratio.unwrap_or(1.0))?),
+        };
+
+        let pitch = match args.pitch.pitch {
+            Some(ratio) => positive("--pitch", ratio)?,
+            None => {
+                let semitones =
+                    args.pitch.semitones.unwrap_or(0.0) + args.pitch.cents.unwrap_or(0.0) / 100.0;
+                positive("pitch", tempo::pitch_for_semitones(semitones))?
+            }
+        };
+
+        Ok(Self {
+            speed,
+            pitch,
+            format: args.format,
+            dither: !args.no_dither,
+            overwrite: args.overwrite,
+        })
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+/// Decodes, stretches and writes a single file.
+fn process_file(input: &Path, output: &Path, settings: &Settings) -> Result<(), Box<dyn Error>> {
+    if !settings.overwrite && output.exists() {
+        return Err(format!(
+            "output file '{}' exists: use --overwrite to replace it",
+            output.display()
+        )
+        .into());
+    }
+    let audio = io::decode_file(input)?;
+    let sample_rate = audio.sample_rate;
+    let stretched = match settings.speed {
+        Speed::Ratio(speed) => offline::stretch_source(audio, sample_rate, speed, settings.pitch)?,
+        Speed::Duration(seconds) => {
+            let target_len =
+                tempo::frames_for_duration(Duration::from_secs_f64(seconds), sample_rate);
+            let semitones = tempo::semitones_for_pitch(settings.pitch);
+            offline::stretch_to_duration(audio, sample_rate, target_len, semitones)?
+        }
+        Speed::Tempo {
+            source_bpm,
+            target_bpm,
+        } => {
+            let semitones = tempo::semitones_for_pitch(settings.pitch);
+            offline::stretch_to_bpm(audio, sample_rate, source_bpm, target_bpm, semitones)?
+        }
+    };
+
+    if let Some(parent) = output.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    wav::write(
+        output,
+        &stretched,
+        sample_rate,
+        settings.format,
+        settings.dither,
+    )?;
+    Ok(())
+}
+
+/// Returns the input and output paths of all files which should be processed.
+fn collect_jobs(input: &Path, output: &Path) -> Result<Vec<(PathBuf, PathBuf)>, Box<dyn Error>> {
+    if !input.is_dir() {
+        return Ok(vec![(input.to_path_buf(), output.to_path_buf())]);
+    }
+    if output.is_file() {
+        return Err(format!(
+            "output '{}' must be a directory when the input is a directory",
+            output.display()
+        )
+        .into());
+    }
+    let mut jobs: Vec<(PathBuf, PathBuf)> = Vec::new();
+    let mut outputs = HashMap::new();
+    for entry in walkdir::WalkDir::new(input).sort_by_file_name() {
+        let entry = entry?;
+        let is_audio_file = entry.file_type().is_file()
+            && entry
+                .path()
+                .extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|e| EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()));
+        if is_audio_file {
+            let relative_path = entry.path().strip_prefix(input)?;
+            let output_path = output.join(relative_path).with_extension("wav");
+            // e.g. `a.wav` and `a.flac` in the same folder
+            if let Some(other) = outputs.insert(output_path.clone(), jobs.len()) {
+                return Err(format!(
+                    "'{}' and '{}' would both be written to '{}'",
+                    jobs[other].0.display(),
+                    entry.path().display(),
+                    output_path.display()
+                )
+                .into());
+            }
+            jobs.push((entry.path().to_path_buf(), output_path));
+        }
+    }
+    Ok(jobs)
+}
+
+// -------------------------------------------------------------------------------------------------
+
+fn main() -> ExitCode {
+    let args = Arguments::parse();
+    let settings = Settings::new(&args).unwrap_or_else(|err| {
+        Arguments::command()
+            .error(ErrorKind::ValueValidation, err)
+            .exit()
+    });
+
+    let jobs = match collect_jobs(&args.input, &args.output) {
+        Ok(jobs) => jobs,
+        Err(err) => {
+            eprintln!("error: {err}");
+            return ExitCode::from(EXIT_FAILURE);
+        }
+    };
+    if jobs.is_empty() {
+        eprintln!("error: no audio files found in '{}'", args.input.display());
+        return ExitCode::from(EXIT_FAILURE);
+    }
+
+    let progress = if args.quiet {
+        ProgressBar::hidden()
+    } else {
+        ProgressBar::new(jobs.len() as u64)
+    };
+    progress.set_style(
+        ProgressStyle::with_template("[{elapsed_precise}] {bar:40} {pos}/{len} {wide_msg}")
+            .expect("valid progress template"),
+    );
+
+    let mut failures = 0;
+    for (input, output) in &jobs {
+        progress.set_message(input.display().to_string());
+        if let Err(err) = process_file(input, output, &settings) {
+            progress.suspend(|| eprintln!("error: '{}': {err}", input.display()));
+            failures += 1;
+        }
+        progress.inc(1);
+    }
+    progress.finish_and_clear();
+
+    if failures > 0 {
+        eprintln!("{failures} of {} files failed", jobs.len());
+        return ExitCode::from(EXIT_FAILURE);
+    }
+    if !args.quiet {
+        println!("Processed {} files", jobs.len());
+    }
+    ExitCode::SUCCESS
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(args: &[&str]) -> Result<Settings, String> {
+        let args = Arguments::try_parse_from(["bungee", "in.wav", "out.wav"].iter().chain(args))
+            .map_err(|err| err.to_string())?;
+        Settings::new(&args)
+    }
+
+    #[test]
+    fn speed_and_pitch_arguments() {
+        let defaults = settings(&[]).unwrap();
+        assert_eq!(defaults.speed, Speed::Ratio(1.0));
+        assert_eq!(defaults.pitch, 1.0);
+        assert_eq!(defaults.format, SampleFormat::Int16);
+        assert!(defaults.dither);
+
+        assert_eq!(
+            settings(&["--speed", "1.5"]).unwrap().speed,
+            Speed::Ratio(1.5)
+        );
+        assert_eq!(
+            settings(&["--duration", "2"]).unwrap().speed,
+            Speed::Duration(2.0)
+        );
+        assert_eq!(
+            settings(&["--bpm", "128", "--source-bpm", "120"])
+                .unwrap()
+                .speed,
+            Speed::Tempo {
+                source_bpm: 120.0,
+                target_bpm: 128.0
+            }
+        );
+
+        assert_eq!(settings(&["--pitch", "2"]).unwrap().pitch, 2.0);
+        assert!((settings(&["--semitones", "-12"]).unwrap().pitch - 0.5).abs() < 1e-12);
+        let detuned = settings(&["--semitones", "1", "--cents", "-50"])
+            .unwrap()
+            .pitch;
+        assert!((detuned - 2f64.powf(0.5 / 12.0)).abs() < 1e-12);
+
+        assert_eq!(
+            settings(&["-f", "float", "--no-dither"]).unwrap().format,
+            SampleFormat::Float32
+        );
+
+        // invalid and conflicting arguments
+        assert!(settings(&["--speed", "0"]).is_err());
+        assert!(settings(&["--speed", "2", "--duration", "1"]).is_err());
+        assert!(settings(&["--bpm", "120"]).is_err());
+        assert!(settings(&["--pitch", "2", "--semitones", "1"]).is_err());
+        assert!(settings(&["-f", "8"]).is_err());
+    }
+
+    #[test]
+    fn batch_jobs() {
+        let dir = std::env::temp_dir().join(format!("bungee-cli-batch-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        for file in ["a.wav", "b.txt", "sub/c.FLAC"] {
+            std::fs::write(dir.join(file), []).unwrap();
+        }
+
+        let jobs = collect_jobs(&dir, Path::new("out")).unwrap();
+        assert_eq!(
+            jobs,
+            vec![
+                (dir.join("a.wav"), PathBuf::from("out/a.wav")),
+                (dir.join("sub/c.FLAC"), PathBuf::from("out/sub/c.wav")),
+            ]
+        );
+        assert!(collect_jobs(&dir, &dir.join("a.wav")).is_err());
+
+        // files which only differ in their extension would write the same output file
+        std::fs::write(dir.join("sub/c.mp3"), []).unwrap();
+        let err = collect_jobs(&dir, Path::new("out"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("c.FLAC") && err.contains("c.mp3") && err.contains("c.wav"));
+        std::fs::remove_file(dir.join("sub/c.mp3")).unwrap();
+
+        // failed files get reported
+        let settings = settings(&[]).unwrap();
+        assert!(process_file(&dir.join("a.wav"), &dir.join("out.wav"), &settings).is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,173 @@
+//! WAV output with integer or float sample formats and TPDF dither.
+
+use std::path::Path;
+
+use clap::ValueEnum;
+
+// -------------------------------------------------------------------------------------------------
+
+/// Sample format of the written WAV files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SampleFormat {
+    /// 16-bit integer PCM
+    #[value(name = "16")]
+    Int16,
+    /// 24-bit integer PCM
+    #[value(name = "24")]
+    Int24,
+    /// 32-bit integer PCM
+    #[value(name = "32")]
+    Int32,
+    /// 32-bit IEEE float
+    #[value(name = "float")]
+    Float32,
+}
+
+impl SampleFormat {
+    /// Returns the number of bits per sample.
+    pub fn bits(self) -> u16 {
+        match self {
+            SampleFormat::Int16 => 16,
+            SampleFormat::Int24 => 24,
+            SampleFormat::Int32 | SampleFormat::Float32 => 32,
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+/// Quantizes float samples to signed integers, with optional triangular (TPDF) dither of
+/// +/- 1 LSB, which decorrelates the quantization error from the signal.
+pub struct Quantizer {
+    scale: f64,
+    min: f64,
+    max: f64,
+    dither: bool,
+    seed: u32,
+}
+
+impl Quantizer {
+    pub fn new(bits: u16, dither: bool) -> Self {
+        let scale = (1u64 << (bits - 1)) as f64;
+        Self {
+            scale,
+            min: -scale,
+            max: scale - 1.0,
+            dither,
+            seed: 0x9E37_79B9,
+        }
+    }
+
+    /// Quantizes a sample in range `[-1, 1]`, clipping samples outside of the range.
+    pub fn quantize(&mut self, sample: f32) -> i32 {
+        let mut value = sample as f64 * self.scale;
+        if self.dither {
+            value += self.random() - self.random();
+        }
+        value.round().clamp(self.min, self.max) as i32
+    }
+
+    /// Returns a uniformly distributed random number in range `[0, 1)` (xorshift32).
+    fn random(&mut self) -> f64 {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 17;
+        self.seed ^= self.seed << 5;
+        self.seed as f64 / (u32::MAX as f64 + 1.0)
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+/// Writes planar audio into a WAV file with the given sample format.
+pub fn write(
+    path: &Path,
+    channels: &[Vec<f32>],
+    sample_rate: usize,
+    format: SampleFormat,
+    dither: bool,
+) -> Result<(), hound::Error> {
+    let spec = hound::WavSpec {
+        channels: channels.len() as u16,
+        sample_rate: sample_rate as u32,
+        bits_per_sample: format.bits(),
+        sample_format: match format {
+            SampleFormat::Float32 => hound::SampleFormat::Float,
+            _ => hound::SampleFormat::Int,
+        },
+    };
+    let mut writer = hound::WavWriter::create(path, spec)?;
+    let frame_count = channels.first().map_or(0, Vec::len);
+    if format == SampleFormat::Float32 {
+        for frame in 0..frame_count {
+            for channel in channels {
+                writer.write_sample(channel[frame])?;
+            }
+        }
+    } else {
+        let mut quantizer = Quantizer::new(format.bits(), dither);
+        for frame in 0..frame_count {
+            for channel in channels {
+                writer.write_sample(quantizer.quantize(channel[frame]))?;
+            }
+        }
+    }
+    writer.finalize()
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quantizer() {
+        // without dither: rounding and clipping
+        let mut quantizer = Quantizer::new(16, false);
+        assert_eq!(quantizer.quantize(0.0), 0);
+        assert_eq!(quantizer.quantize(0.5), 16384);
+        assert_eq!(quantizer.quantize(1.0), 32767);
+        assert_eq!(quantizer.quantize(2.0), 32767);
+        assert_eq!(quantizer.quantize(-1.0), -32768);
+        assert_eq!(quantizer.quantize(-2.0), -32768);
+
+        // TPDF dither: at most +/- 1 LSB, zero mean
+        let mut quantizer = Quantizer::new(16, true);
+        let sample = 1000.25 / 32768.0;
+        let values = (0..10000)
+            .map(|_| quantizer.quantize(sample))
+            .collect::<Vec<_>>();
+        assert!(values.iter().all(|v| (999..=1002).contains(v)));
+        let mean = values.iter().map(|v| *v as f64).sum::<f64>() / values.len() as f64;
+        assert!((mean - 1000.25).abs() < 0.05);
+        assert!(values.iter().any(|v| *v != values[0]));
+
+        assert_eq!(Quantizer::new(24, false).quantize(1.0), (1 << 23) - 1);
+        assert_eq!(Quantizer::new(32, false).quantize(-1.0), i32::MIN);
+    }
+
+    #[test]
+    fn write_formats() {
+        let channels = vec![vec![0.0, 0.5, -0.5, 1.5]; 2];
+        for format in [
+            SampleFormat::Int16,
+            SampleFormat::Int24,
+            SampleFormat::Int32,
+            SampleFormat::Float32,
+        ] {
+            let path = std::env::temp_dir().join(format!(
+                "bungee-cli-{}-{}.wav",
+                std::process::id(),
+                format.bits()
+            ));
+            write(&path, &channels, 48000, format, false).unwrap();
+
+            let reader = hound::WavReader::open(&path).unwrap();
+            assert_eq!(reader.spec().channels, 2);
+            assert_eq!(reader.spec().sample_rate, 48000);
+            assert_eq!(reader.spec().bits_per_sample, format.bits());
+            assert_eq!(reader.len(), 8);
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+}
@@ -0,0 +1,303 @@
+//! Real-time safe parameter control for a [`Stream`](crate::Stream), which is owned by an
+//! audio thread, from other threads.
+
+use std::sync::{
+    atomic::{AtomicU32, AtomicU64, AtomicUsize, Ordering},
+    Arc,
+};
+
+use crate::Error;
+
+// -------------------------------------------------------------------------------------------------
+
+/// Parameters which get exchanged between a [`StreamController`] and its stream.
+#[derive(Debug)]
+struct Shared {
+    speed: AtomicU64,
+    pitch: AtomicU64,
+    smoothing: AtomicUsize,
+    jump: AtomicU32,
+}
+
+// -------------------------------------------------------------------------------------------------
+
+/// A clonable handle which controls the speed and pitch of a [`Stream`](crate::Stream) from
+/// any thread, e.g. a UI thread, while an audio thread owns and processes the stream.
+///
+/// Parameters are exchanged via atomics, so neither side blocks or allocates. The stream picks
+/// up new target values at the start of each
+/// [`process_controlled`](crate::Stream::process_controlled) call and, when smoothing is
+/// enabled, ramps towards them over the given number of input frames.
+///
+/// ```rust, no_run
+/// use bungee_rs::Stream;
+///
+/// fn main() -> Result<(), bungee_rs::Error> {
+///     let mut stream = Stream::new(44100, 2, 1024)?;
+///     let controller = stream.controller();
+///
+///     // e.g. on a UI thread:
+///     let ui = controller.clone();
+///     std::thread::spawn(move || {
+///         ui.set_smoothing(4410);
+///         ui.set_speed(0.5).unwrap();
+///     });
+///
+///     // on the audio thread:
+///     let input = vec![vec![0.0f32; 1024]; 2];
+///     let mut output = vec![vec![0.0f32; 4096]; 2];
+///     let frame_count = stream.process_controlled(Some(&input), &mut output, 1024);
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct StreamController {
+    shared: Arc<Shared>,
+}
+
+impl StreamController {
+    fn new() -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                speed: AtomicU64::new(1.0f64.to_bits()),
+                pitch: AtomicU64::new(1.0f64.to_bits()),
+                smoothing: AtomicUsize::new(0),
+                jump: AtomicU32::new(0),
+            }),
+        }
+    }
+
+    /// Returns the target speed.
+    pub fn speed(&self) -> f64 {
+        f64::from_bits(self.shared.speed.load(Ordering::Relaxed))
+    }
+
+    /// Sets the target speed, which is applied at the start of the next processed block.
+    ///
+    /// # Errors
+    /// Returns an error if the speed is not a positive, finite number.
+    pub fn set_speed(&self, speed: f64) -> Result<(), Error> {
+        if !(speed.is_finite() && speed > 0.0) {
+            return Err(Error::InvalidSpeed(speed));
+        }
+        self.shared.speed.store(speed.to_bits(), Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Returns the target pitch.
+    pub fn pitch(&self) -> f64 {
+        f64::from_bits(self.shared.pitch.load(Ordering::Relaxed))
+    }
+
+    /// Sets the target pitch, which is applied at the start of the next processed block.
+    ///
+    /// # Errors
+    /// Returns an error if the pitch is not a positive, finite number.
+    pub fn set_pitch(&self, pitch: f64) -> Result<(), Error> {
+        if !(pitch.is_finite() && pitch > 0.0) {
+            return Err(Error::InvalidPitch(pitch));
+        }
+        self.shared.pitch.store(pitch.to_bits(), Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Returns the smoothing ramp length in input frames.
+    pub fn smoothing(&self) -> usize {
+        self.shared.smoothing.load(Ordering::Relaxed)
+    }
+
+    /// Sets the length of the ramps, in input frames, over which the stream glides to new
+    /// target values. 0 (the default) applies new values immediately.
+    pub fn set_smoothing(&self, frames: usize) {
+        self.shared.smoothing.store(frames, Ordering::Relaxed);
+    }
+
+    /// Makes the stream jump to the current target values at the start of the next processed
+    /// block, cancelling all running ramps. This only affects the speed and pitch: audio which
+    /// is buffered in the stream's stretcher is still rendered.
+    pub fn jump_to_targets(&self) {
+        self.shared.jump.fetch_add(1, Ordering::Release);
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+/// A parameter which glides towards its target value in a fixed number of frames.
+#[derive(Debug, Clone, Copy)]
+struct Ramp {
+    value: f64,
+    target: f64,
+    remaining: usize,
+}
+
+impl Ramp {
+    fn new(value: f64) -> Self {
+        Self {
+            value,
+            target: value,
+            remaining: 0,
+        }
+    }
+
+    /// Retargets the ramp when the target changed and advances it by `frames`.
+    fn advance(&mut self, target: f64, smoothing: usize, frames: usize) {
+        if target != self.target {
+            self.target = target;
+            self.remaining = smoothing;
+        }
+        if self.remaining <= frames {
+            self.value = self.target;
+            self.remaining = 0;
+        } else {
+            self.value += (self.target - self.value) * frames as f64 / self.remaining as f64;
+            self.remaining -= frames;
+        }
+    }
+
+    fn jump(&mut self, target: f64) {
+        *self = Self::new(target);
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+/// Audio thread side of a [`StreamController`], which is owned by the stream.
+#[derive(Debug)]
+pub(crate) struct StreamControls {
+    controller: StreamController,
+    speed: Ramp,
+    pitch: Ramp,
+    jump: u32,
+}
+
+/// Speed and pitch of the next processed block, along with the state of the controls after it.
+/// See [`StreamControls::next_block`].
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ControlBlock {
+    speed: Ramp,
+    pitch: Ramp,
+    jump: u32,
+}
+
+impl ControlBlock {
+    pub fn speed(&self) -> f64 {
+        self.speed.value
+    }
+
+    pub fn pitch(&self) -> f64 {
+        self.pitch.value
+    }
+}
+
+impl StreamControls {
+    pub fn new() -> Self {
+        Self {
+            controller: StreamController::new(),
+            speed: Ramp::new(1.0),
+            pitch: Ramp::new(1.0),
+            jump: 0,
+        }
+    }
+
+    /// Returns a new handle to the controls.
+    pub fn controller(&self) -> StreamController {
+        self.controller.clone()
+    }
+
+    /// Applies pending commands to a copy of the controls and returns the speed and pitch for
+    /// the next block of `frames` input frames. The controls only move on when the block gets
+    /// [committed](Self::commit), so a block which fails to process can be dropped.
+    pub fn next_block(&self, frames: usize) -> ControlBlock {
+        let shared = &self.controller.shared;
+        // load the jump counter first: its Acquire pairs with the Release in `jump_to_targets`,
+        // so the parameters which get loaded afterwards are at least the ones set before it
+        let jump = shared.jump.load(Ordering::Acquire);
+        let speed = f64::from_bits(shared.speed.load(Ordering::Relaxed));
+        let pitch = f64::from_bits(shared.pitch.load(Ordering::Relaxed));
+        let mut block = ControlBlock {
+            speed: self.speed,
+            pitch: self.pitch,
+            jump,
+        };
+        if jump != self.jump {
+            block.speed.jump(speed);
+            block.pitch.jump(pitch);
+        }
+        let smoothing = shared.smoothing.load(Ordering::Relaxed);
+        block.speed.advance(speed, smoothing, frames);
+        block.pitch.advance(pitch, smoothing, frames);
+        block
+    }
+
+    /// Moves the controls on to their state after the given, processed block.
+    pub fn commit(&mut self, block: ControlBlock) {
+        self.speed = block.speed;
+        self.pitch = block.pitch;
+        self.jump = block.jump;
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn controller_ramps() {
+        let mut controls = StreamControls::new();
+        let controller = controls.controller();
+        let mut next_block = |frames| {
+            let block = controls.next_block(frames);
+            controls.commit(block);
+            (block.speed(), block.pitch())
+        };
+        assert_eq!(next_block(100), (1.0, 1.0));
+
+        // immediate changes
+        controller.set_speed(2.0).unwrap();
+        controller.set_pitch(0.5).unwrap();
+        assert_eq!(next_block(100), (2.0, 0.5));
+
+        // smoothed changes
+        controller.set_smoothing(400);
+        controller.set_speed(1.0).unwrap();
+        assert_eq!(next_block(100), (1.75, 0.5));
+        assert_eq!(next_block(200), (1.25, 0.5));
+        assert_eq!(next_block(200), (1.0, 0.5));
+
+        // jumps cancel ramps
+        controller.set_speed(3.0).unwrap();
+        assert_eq!(next_block(100).0, 1.5);
+        controller.jump_to_targets();
+        assert_eq!(next_block(100).0, 3.0);
+
+        assert!(matches!(
+            controller.set_speed(0.0),
+            Err(Error::InvalidSpeed(_))
+        ));
+        assert!(matches!(
+            controller.set_pitch(f64::NAN),
+            Err(Error::InvalidPitch(_))
+        ));
+    }
+
+    #[test]
+    fn controller_uncommitted_blocks() {
+        let mut controls = StreamControls::new();
+        let controller = controls.controller();
+        controller.set_smoothing(400);
+        controller.set_speed(2.0).unwrap();
+
+        // blocks which don't get committed leave the controls unchanged
+        assert_eq!(controls.next_block(100).speed(), 1.25);
+        assert_eq!(controls.next_block(100).speed(), 1.25);
+        controller.jump_to_targets();
+        assert_eq!(controls.next_block(100).speed(), 2.0);
+
+        let block = controls.next_block(100);
+        controls.commit(block);
+        controller.set_speed(1.0).unwrap();
+        assert_eq!(controls.next_block(200).speed(), 1.5);
+    }
+}
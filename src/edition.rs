@@ -0,0 +1,132 @@
+//! Bungee edition marker types, which select the Bungee edition of a
+//! [`Stretcher`](crate::Stretcher) or [`Stream`](crate::Stream) at compile time.
+//!
+//! The [`Basic`] edition is always available and the default. The `Pro` edition requires the
+//! `pro` feature, which builds Bungee from a Bungee Pro source tree in `BUNGEE_PRO_DIR`. Both
+//! editions can be used side by side in one binary:
+//!
+//! ```rust, no_run
+//! use bungee_rs::{edition::Basic, Stretcher, StretcherBuilder};
+//!
+//! fn main() -> Result<(), bungee_rs::Error> {
+//!     // Stretchers and streams use the Basic edition by default...
+//!     let basic = Stretcher::new(44100, 2)?;
+//!     // ...or the edition which is selected in their builder, e.g. `bungee_rs::edition::Pro`.
+//!     let explicit = StretcherBuilder::new(44100, 2).edition::<Basic>().build()?;
+//!     assert_eq!(basic.edition(), explicit.edition());
+//!     Ok(())
+//! }
+//! ```
+
+use crate::Edition;
+
+// -------------------------------------------------------------------------------------------------
+
+/// Collects the FFI functions of the `stretcher` and `stream` modules in scope.
+macro_rules! ffi_table {
+    () => {
+        sealed::Ffi {
+            create: stretcher::create,
+            destroy: stretcher::destroy,
+            enable_instrumentation: stretcher::enable_instrumentation,
+            max_input_frame_count: stretcher::max_input_frame_count,
+            preroll: stretcher::preroll,
+            next: stretcher::next,
+            specify_grain: stretcher::specify_grain,
+            analyse_grain: stretcher::analyse_grain,
+            synthesise_grain: stretcher::synthesise_grain,
+            is_flushed: stretcher::is_flushed,
+            stream_create: stream::create,
+            stream_destroy: stream::destroy,
+            stream_process: stream::process,
+            stream_input_position: stream::input_position,
+            stream_output_position: stream::output_position,
+            stream_latency: stream::latency,
+        }
+    };
+}
+
+// -------------------------------------------------------------------------------------------------
+
+/// A Bungee edition marker type: either [`Basic`] or `Pro`.
+///
+/// This trait is sealed and can't be implemented outside of this crate.
+pub trait EditionMarker: sealed::Sealed + Send + Sync + 'static {
+    /// The edition which the marker type selects.
+    const EDITION: Edition;
+}
+
+/// Marker type for the open source Bungee Basic edition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Basic;
+
+impl EditionMarker for Basic {
+    const EDITION: Edition = Edition::Basic;
+}
+
+impl sealed::Sealed for Basic {
+    const FFI: sealed::Ffi = {
+        use bungee_sys::{stream, stretcher};
+        ffi_table!()
+    };
+}
+
+/// Marker type for the commercial Bungee Pro edition.
+#[cfg(feature = "pro")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Pro;
+
+#[cfg(feature = "pro")]
+impl EditionMarker for Pro {
+    const EDITION: Edition = Edition::Pro;
+}
+
+#[cfg(feature = "pro")]
+impl sealed::Sealed for Pro {
+    const FFI: sealed::Ffi = {
+        use bungee_sys::pro::{stream, stretcher};
+        ffi_table!()
+    };
+}
+
+// -------------------------------------------------------------------------------------------------
+
+pub(crate) mod sealed {
+    use std::ffi::{c_double, c_float, c_int, c_uchar};
+
+    use bungee_sys::{
+        BungeeStream, BungeeStretcher, InputChunk, OutputChunk, Request, SampleRates,
+    };
+
+    pub trait Sealed {
+        /// The edition's bungee-sys functions.
+        const FFI: Ffi;
+    }
+
+    /// Function table of an edition's bungee-sys `stretcher` and `stream` functions.
+    pub struct Ffi {
+        pub create: fn(SampleRates, c_int, c_int) -> *mut BungeeStretcher,
+        pub destroy: fn(*mut BungeeStretcher),
+        pub enable_instrumentation: fn(*mut BungeeStretcher, c_int),
+        pub max_input_frame_count: fn(*const BungeeStretcher) -> c_int,
+        pub preroll: fn(*const BungeeStretcher, *mut Request),
+        pub next: fn(*const BungeeStretcher, *mut Request),
+        pub specify_grain: fn(*mut BungeeStretcher, *const Request, c_double) -> InputChunk,
+        pub analyse_grain: fn(*mut BungeeStretcher, *const c_float, isize, c_int, c_int),
+        pub synthesise_grain: fn(*mut BungeeStretcher, *mut OutputChunk),
+        pub is_flushed: fn(*const BungeeStretcher) -> c_uchar,
+        pub stream_create: fn(*mut BungeeStretcher, c_int, c_int) -> *mut BungeeStream,
+        pub stream_destroy: fn(*mut BungeeStream),
+        pub stream_process: fn(
+            *mut BungeeStream,
+            *const *const c_float,
+            *mut *mut c_float,
+            c_int,
+            c_double,
+            c_double,
+        ) -> c_int,
+        pub stream_input_position: fn(*const BungeeStream) -> c_int,
+        pub stream_output_position: fn(*const BungeeStream) -> c_double,
+        pub stream_latency: fn(*const BungeeStream) -> c_double,
+    }
+}
@@ -0,0 +1,192 @@
+//! Breakpoint envelopes for time-varying speed and pitch automation.
+
+use crate::Error;
+
+// -------------------------------------------------------------------------------------------------
+
+/// Shape of an envelope segment, which runs from the previous breakpoint to a breakpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Curve {
+    /// Linear interpolation of values.
+    #[default]
+    Linear,
+    /// Exponential (geometric) interpolation of values, which results in perceptually linear
+    /// speed or pitch sweeps. Falls back to linear interpolation unless both values are > 0.
+    Exponential,
+    /// Holds the previous value until the breakpoint is reached.
+    Step,
+}
+
+// -------------------------------------------------------------------------------------------------
+
+/// A single envelope breakpoint.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Breakpoint {
+    /// Time of the breakpoint in output frames.
+    pub time: f64,
+    /// Value at the breakpoint.
+    pub value: f64,
+    /// Shape of the segment which ends at this breakpoint.
+    pub curve: Curve,
+}
+
+// -------------------------------------------------------------------------------------------------
+
+/// A breakpoint envelope in output time, used to automate `Request::speed` or `Request::pitch`.
+///
+/// ```rust
+/// use bungee_rs::envelope::{Curve, Envelope};
+///
+/// fn main() -> Result<(), bungee_rs::Error> {
+///     // Tape stop: slow down from normal speed to 10% within one second at 44.1kHz.
+///     let speed = Envelope::new(1.0).with_point(44100.0, 0.1, Curve::Exponential)?;
+///     assert_eq!(speed.value_at(0.0), 1.0);
+///     assert!((speed.value_at(22050.0) - 0.1f64.sqrt()).abs() < 1e-9);
+///     assert_eq!(speed.value_at(88200.0), 0.1);
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Envelope {
+    initial_value: f64,
+    points: Vec<Breakpoint>,
+}
+
+impl Envelope {
+    /// Creates a new envelope with the given constant value and no breakpoints.
+    pub fn new(initial_value: f64) -> Self {
+        Self {
+            initial_value,
+            points: Vec::new(),
+        }
+    }
+
+    /// Adds a breakpoint, builder style. See [`Self::add_point`].
+    ///
+    /// # Errors
+    /// See [`Self::add_point`].
+    pub fn with_point(mut self, time: f64, value: f64, curve: Curve) -> Result<Self, Error> {
+        self.add_point(time, value, curve)?;
+        Ok(self)
+    }
+
+    /// Adds a breakpoint at the given output frame time. Returns the breakpoint's index.
+    /// Points are kept sorted by time, and points with equal times are kept in insertion order,
+    /// which allows creating jumps.
+    ///
+    /// # Errors
+    /// Returns an error if the time is negative or not finite, or if the value is not finite.
+    pub fn add_point(&mut self, time: f64, value: f64, curve: Curve) -> Result<usize, Error> {
+        if !(time.is_finite() && time >= 0.0 && value.is_finite()) {
+            return Err(Error::InvalidBreakpoint { time, value });
+        }
+        let index = self.points.partition_point(|p| p.time <= time);
+        self.points.insert(index, Breakpoint { time, value, curve });
+        Ok(index)
+    }
+
+    /// Returns the envelope's value before the first breakpoint.
+    pub fn initial_value(&self) -> f64 {
+        self.initial_value
+    }
+
+    /// Returns all breakpoints, sorted by time.
+    pub fn points(&self) -> &[Breakpoint] {
+        &self.points
+    }
+
+    /// Returns an iterator over all values of the envelope, including the initial value.
+    pub fn values(&self) -> impl Iterator<Item = f64> + '_ {
+        std::iter::once(self.initial_value).chain(self.points.iter().map(|p| p.value))
+    }
+
+    /// Returns the envelope's value at the given output frame time. The envelope starts with its
+    /// initial value at time 0 and holds the last breakpoint's value after its time.
+    pub fn value_at(&self, time: f64) -> f64 {
+        let index = self.points.partition_point(|p| p.time <= time);
+        let Some(next) = self.points.get(index) else {
+            return self.points.last().map_or(self.initial_value, |p| p.value);
+        };
+        let (prev_time, prev_value) = match index {
+            0 => (0.0, self.initial_value),
+            _ => (self.points[index - 1].time, self.points[index - 1].value),
+        };
+        if time < prev_time {
+            // before time 0
+            return prev_value;
+        }
+        let x = (time - prev_time) / (next.time - prev_time);
+        match next.curve {
+            Curve::Linear => prev_value + (next.value - prev_value) * x,
+            Curve::Exponential if prev_value > 0.0 && next.value > 0.0 => {
+                prev_value * (next.value / prev_value).powf(x)
+            }
+            Curve::Exponential => prev_value + (next.value - prev_value) * x,
+            Curve::Step => prev_value,
+        }
+    }
+}
+
+impl From<f64> for Envelope {
+    fn from(value: f64) -> Self {
+        Self::new(value)
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn envelope_values() {
+        let envelope = Envelope::new(1.0)
+            .with_point(100.0, 2.0, Curve::Linear)
+            .and_then(|e| e.with_point(300.0, 8.0, Curve::Exponential))
+            .and_then(|e| e.with_point(400.0, 0.5, Curve::Step))
+            // jump
+            .and_then(|e| e.with_point(500.0, 1.0, Curve::Step))
+            .and_then(|e| e.with_point(500.0, 3.0, Curve::Step))
+            .unwrap();
+
+        assert_eq!(envelope.value_at(-10.0), 1.0);
+        assert_eq!(envelope.value_at(0.0), 1.0);
+        assert_eq!(envelope.value_at(50.0), 1.5);
+        assert_eq!(envelope.value_at(100.0), 2.0);
+        assert!((envelope.value_at(200.0) - 4.0).abs() < 1e-9);
+        assert_eq!(envelope.value_at(399.0), 8.0);
+        assert_eq!(envelope.value_at(400.0), 0.5);
+        assert_eq!(envelope.value_at(499.0), 0.5);
+        assert_eq!(envelope.value_at(500.0), 3.0);
+        assert_eq!(envelope.value_at(1e9), 3.0);
+
+        assert_eq!(Envelope::new(0.5).value_at(1000.0), 0.5);
+        assert_eq!(
+            envelope.values().collect::<Vec<_>>(),
+            [1.0, 2.0, 8.0, 0.5, 1.0, 3.0]
+        );
+    }
+
+    #[test]
+    fn envelope_invalid_points() {
+        let mut envelope = Envelope::new(1.0);
+        assert_eq!(envelope.add_point(200.0, 2.0, Curve::Linear), Ok(0));
+        assert_eq!(envelope.add_point(100.0, 3.0, Curve::Linear), Ok(0));
+        for (time, value) in [
+            (f64::NAN, 1.0),
+            (f64::INFINITY, 1.0),
+            (-1.0, 1.0),
+            (50.0, f64::NAN),
+            (50.0, f64::NEG_INFINITY),
+        ] {
+            assert!(matches!(
+                envelope.add_point(time, value, Curve::Linear),
+                Err(Error::InvalidBreakpoint { .. })
+            ));
+        }
+        // rejected points leave the sorted breakpoints untouched
+        let times = envelope.points().iter().map(|p| p.time).collect::<Vec<_>>();
+        assert_eq!(times, [100.0, 200.0]);
+    }
+}
@@ -0,0 +1,127 @@
+use std::fmt;
+
+// -------------------------------------------------------------------------------------------------
+
+/// Errors reported by the `bungee-rs` wrappers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Error {
+    /// A sample rate is zero.
+    InvalidSampleRate(usize),
+    /// The channel count is zero.
+    InvalidChannelCount(usize),
+    /// The synthesis hop adjustment is out of range.
+    InvalidSynthesisHopAdjust(i32),
+    /// An input frame count is zero or exceeds the configured maximum.
+    InvalidInputFrameCount(usize),
+    /// An output frame count is not a positive, finite number or exceeds the configured maximum.
+    InvalidOutputFrameCount(f64),
+    /// The Bungee library failed to create an instance of the named object, e.g. "stretcher" or
+    /// "stream".
+    CreationFailed(&'static str),
+    /// A channel buffer holds less frames than required.
+    BufferTooSmall {
+        channel: usize,
+        len: usize,
+        required: usize,
+    },
+    /// The number of channel buffers doesn't match the stretcher's channel count.
+    ChannelMismatch { expected: usize, actual: usize },
+    /// A channel buffer's length differs from the length of the first channel.
+    ChannelLengthMismatch {
+        channel: usize,
+        len: usize,
+        expected: usize,
+    },
+    /// A pitch is not a positive, finite number.
+    InvalidPitch(f64),
+    /// A speed is not a finite number.
+    InvalidSpeed(f64),
+    /// A tempo in BPM is not a positive, finite number.
+    InvalidTempo(f64),
+    /// A frame range is empty or exceeds the input audio.
+    InvalidRange { start: usize, end: usize },
+    /// An envelope breakpoint's time is negative or not finite, or its value is not finite.
+    InvalidBreakpoint { time: f64, value: f64 },
+    /// A warp marker is not finite or breaks the warp map's monotonicity.
+    InvalidWarpMarker { source: f64, target: f64 },
+    /// A grain position is infinite. Note: `NaN` positions are valid and used for flushing.
+    NonFinitePosition(f64),
+    /// Muted head and tail frames exceed the input chunk's length.
+    InvalidMuteRange {
+        head: usize,
+        tail: usize,
+        len: usize,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidSampleRate(rate) => {
+                write!(f, "invalid sample rate: got {rate}, but need rate > 0")
+            }
+            Error::InvalidChannelCount(count) => {
+                write!(f, "invalid channel count: got {count}, but need channels > 0")
+            }
+            Error::InvalidSynthesisHopAdjust(adjust) => {
+                write!(f, "invalid synthesis hop adjustment: '{adjust}'")
+            }
+            Error::InvalidInputFrameCount(count) => {
+                write!(f, "invalid input frame count: got {count} frames")
+            }
+            Error::InvalidOutputFrameCount(count) => {
+                write!(f, "invalid output frame count: got {count} frames")
+            }
+            Error::CreationFailed(object) => write!(f, "failed to create Bungee {object}"),
+            Error::BufferTooSmall {
+                channel,
+                len,
+                required,
+            } => write!(
+                f,
+                "channel[{channel}].len() ({len}) is less than the required frame count ({required})"
+            ),
+            Error::ChannelMismatch { expected, actual } => write!(
+                f,
+                "channel count mismatch: got {actual} channels, but stretcher has {expected}"
+            ),
+            Error::ChannelLengthMismatch {
+                channel,
+                len,
+                expected,
+            } => write!(
+                f,
+                "channel length mismatch: channel[{channel}].len() is {len}, but channel[0].len() is {expected}"
+            ),
+            Error::InvalidPitch(pitch) => {
+                write!(f, "invalid pitch: pitch must be > 0 but is '{pitch}'")
+            }
+            Error::InvalidSpeed(speed) => {
+                write!(f, "invalid speed: speed must be finite but is '{speed}'")
+            }
+            Error::InvalidTempo(bpm) => {
+                write!(f, "invalid tempo: BPM must be > 0 but is '{bpm}'")
+            }
+            Error::InvalidRange { start, end } => {
+                write!(f, "invalid frame range: {start}..{end}")
+            }
+            Error::InvalidBreakpoint { time, value } => write!(
+                f,
+                "invalid envelope breakpoint: {value} at {time} must be finite, at a time >= 0"
+            ),
+            Error::InvalidWarpMarker { source, target } => write!(
+                f,
+                "invalid warp marker: {source} -> {target} must be finite and lie strictly between its neighbours"
+            ),
+            Error::NonFinitePosition(position) => {
+                write!(f, "invalid position: position must be finite or NaN but is '{position}'")
+            }
+            Error::InvalidMuteRange { head, tail, len } => write!(
+                f,
+                "invalid mute range: muting {head} + {tail} frames exceeds the input chunk's {len} frames"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
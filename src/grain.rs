@@ -0,0 +1,36 @@
+use crate::{edition::EditionMarker, source::AudioSource, InputChunk, Stretcher};
+
+// -------------------------------------------------------------------------------------------------
+
+/// Planar scratch buffer which feeds input chunks from an [`AudioSource`] to the stretcher,
+/// muting regions which lie outside of the source.
+pub(crate) struct GrainInput {
+    data: Vec<f32>,
+    channel_stride: usize,
+}
+
+impl GrainInput {
+    pub fn new<E: EditionMarker>(stretcher: &Stretcher<E>) -> Self {
+        let channel_stride = stretcher.max_input_frame_count();
+        let data = vec![0.0; channel_stride * stretcher.num_channels()];
+        Self {
+            data,
+            channel_stride,
+        }
+    }
+
+    pub fn analyse<E: EditionMarker, S: AudioSource + ?Sized>(
+        &mut self,
+        stretcher: &mut Stretcher<E>,
+        source: &mut S,
+        chunk: InputChunk,
+    ) {
+        let mute = chunk.mute_range(source.len());
+        let begin = (chunk.begin + mute.head as isize) as usize;
+        let end = (chunk.end - mute.tail as isize) as usize;
+        if end > begin {
+            source.read(begin, end, &mut self.data[mute.head..], self.channel_stride);
+        }
+        stretcher.analyse_grain_with_mute(&self.data, self.channel_stride, mute);
+    }
+}
@@ -0,0 +1,156 @@
+use std::{ffi::CStr, fmt};
+
+// -------------------------------------------------------------------------------------------------
+
+/// Edition of the linked Bungee library.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Edition {
+    /// The open source Bungee edition.
+    Basic,
+    /// The commercial Bungee Pro edition.
+    Pro,
+    /// An edition which this crate doesn't know, with the name the library reported, or
+    /// "unknown" when it reported none.
+    Unknown(&'static str),
+}
+
+impl fmt::Display for Edition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Edition::Basic => write!(f, "Basic"),
+            Edition::Pro => write!(f, "Pro"),
+            Edition::Unknown(name) => write!(f, "{name}"),
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+/// Returns the release number of the linked Bungee library, for example "1.2.3".
+pub fn version() -> &'static str {
+    // Safety: Bungee returns a pointer to a static, nul-terminated string.
+    unsafe { static_str(bungee_sys::stretcher::version()) }
+}
+
+/// Returns the edition of the linked Bungee library, or [`Edition::Unknown`] when the library
+/// reports an edition which this crate doesn't know.
+pub fn edition() -> Edition {
+    // Safety: Bungee returns a pointer to a static, nul-terminated string.
+    parse_edition(unsafe { static_str(bungee_sys::stretcher::edition()) })
+}
+
+fn parse_edition(name: &'static str) -> Edition {
+    match name {
+        "Basic" => Edition::Basic,
+        "Pro" => Edition::Pro,
+        name => Edition::Unknown(name),
+    }
+}
+
+unsafe fn static_str(ptr: *const std::ffi::c_char) -> &'static str {
+    if ptr.is_null() {
+        return "unknown";
+    }
+    CStr::from_ptr(ptr).to_str().unwrap_or("unknown")
+}
+
+// -------------------------------------------------------------------------------------------------
+
+/// Describes the build of `bungee-rs` and the linked Bungee library, e.g. to record which DSP
+/// build produced a file in logs or crash reports.
+///
+/// ```rust, no_run
+/// let info = bungee_rs::build_info();
+/// // "bungee-rs 0.2.0, Bungee Basic 1.2.3 (Release, -O3 -DNDEBUG), instrumentation available"
+/// println!("{info}");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BuildInfo {
+    /// Version of the `bungee-rs` crate.
+    pub crate_version: &'static str,
+    /// Release number of the linked Bungee library.
+    pub bungee_version: &'static str,
+    /// Edition of the linked Bungee library.
+    pub edition: Edition,
+    /// CMake build type of the Bungee library, e.g. "Release". None when a system or
+    /// prebuilt library is linked, see the `system` feature.
+    pub build_profile: Option<&'static str>,
+    /// C++ compiler flags from the cmake cache of the Bungee library build. None when a system
+    /// or prebuilt library is linked. See `bungee_sys::BUILD_FLAGS`.
+    pub compiler_flags: Option<&'static str>,
+    /// Whether the linked library supports instrumentation, see
+    /// [`StretcherBuilder::instrumentation`](crate::StretcherBuilder::instrumentation).
+    pub instrumentation: bool,
+}
+
+impl BuildInfo {
+    /// Returns the build info of the running binary.
+    pub fn current() -> Self {
+        Self {
+            crate_version: env!("CARGO_PKG_VERSION"),
+            bungee_version: version(),
+            edition: edition(),
+            build_profile: bungee_sys::BUILD_PROFILE,
+            compiler_flags: bungee_sys::BUILD_FLAGS,
+            instrumentation: bungee_sys::INSTRUMENTATION,
+        }
+    }
+}
+
+impl fmt::Display for BuildInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "bungee-rs {}, Bungee {} {}",
+            self.crate_version, self.edition, self.bungee_version
+        )?;
+        match (self.build_profile, self.compiler_flags) {
+            (Some(profile), Some(flags)) => write!(f, " ({profile}, {flags})"),
+            (Some(profile), None) => write!(f, " ({profile})"),
+            _ => write!(f, " (prebuilt)"),
+        }?;
+        if self.instrumentation {
+            write!(f, ", instrumentation available")?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the build info of the running binary. See [`BuildInfo`].
+pub fn build_info() -> BuildInfo {
+    BuildInfo::current()
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_info() {
+        assert!(!version().is_empty());
+        assert_eq!(edition(), Edition::Basic);
+
+        let info = BuildInfo::current();
+        assert_eq!(info.crate_version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(info.bungee_version, version());
+        assert_eq!(info.instrumentation, bungee_sys::INSTRUMENTATION);
+        assert!(info.to_string().starts_with("bungee-rs "));
+        assert_eq!(
+            info.to_string().ends_with(", instrumentation available"),
+            info.instrumentation
+        );
+        // the vendored library is built by this crate
+        #[cfg(not(feature = "system"))]
+        assert!(info.build_profile.is_some() && info.compiler_flags.is_some());
+    }
+
+    #[test]
+    fn edition_names() {
+        assert_eq!(parse_edition("Basic"), Edition::Basic);
+        assert_eq!(parse_edition("Pro"), Edition::Pro);
+        assert_eq!(parse_edition("unknown"), Edition::Unknown("unknown"));
+        assert_eq!(Edition::Unknown("Lite").to_string(), "Lite");
+    }
+}
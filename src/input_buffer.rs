@@ -0,0 +1,295 @@
+//! Sliding window input buffers for stretching unbounded live input with the low-level
+//! [`Stretcher`] API.
+
+use crate::{edition::EditionMarker, realtime, Error, InputChunk, Request, Stretcher};
+
+// -------------------------------------------------------------------------------------------------
+
+/// A ring buffer which retains a rolling window of planar input audio and feeds grains from it.
+///
+/// Input gets appended with [`push`](Self::push), which drops the oldest frames once the buffer
+/// is full. Grains may request any input position: frames within the retained history are read
+/// from the buffer, all others (not yet pushed or already dropped) get muted. The buffer tells
+/// the stretcher where its window starts, so input chunks stay small relative offsets, no matter
+/// how long the input runs.
+///
+/// ```rust, no_run
+/// use bungee_rs::{input_buffer::GrainInputBuffer, Request, Stretcher};
+///
+/// fn main() -> Result<(), bungee_rs::Error> {
+///     let mut stretcher = Stretcher::new(44100, 1)?;
+///     // Retain 5 seconds of input history.
+///     let mut buffer = GrainInputBuffer::new(&stretcher, 5 * 44100);
+///
+///     let mut request = Request {
+///         pitch: 1.0,
+///         speed: 0.5,
+///         position: 0.0,
+///         reset: true,
+///     };
+///     stretcher.preroll(&mut request);
+///
+///     // Push live input as it arrives...
+///     buffer.push(&[vec![0.0f32; 1024]])?;
+///     // ...and render grains as long as their input is available.
+///     while buffer.covers(&stretcher, &request) {
+///         buffer.analyse(&mut stretcher, &request)?;
+///         let _output = stretcher.synthesise_grain();
+///         stretcher.next(&mut request);
+///     }
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct GrainInputBuffer {
+    channels: Vec<Vec<f32>>,
+    capacity: usize,
+    end_position: usize,
+    history_len: usize,
+    scratch: Vec<f32>,
+    scratch_stride: usize,
+}
+
+impl GrainInputBuffer {
+    /// Creates a new, empty buffer for the given stretcher, which retains at least `history`
+    /// frames of input. The buffer always retains at least the stretcher's max input frame
+    /// count, so that a single grain can be served entirely.
+    pub fn new<E: EditionMarker>(stretcher: &Stretcher<E>, history: usize) -> Self {
+        let num_channels = stretcher.num_channels();
+        let scratch_stride = stretcher.max_input_frame_count();
+        let capacity = history.max(scratch_stride).max(1);
+        Self {
+            channels: vec![vec![0.0; capacity]; num_channels],
+            capacity,
+            end_position: 0,
+            history_len: 0,
+            scratch: vec![0.0; scratch_stride * num_channels],
+            scratch_stride,
+        }
+    }
+
+    /// Returns the buffer's channel count.
+    pub fn num_channels(&self) -> usize {
+        self.channels.len()
+    }
+
+    /// Returns the maximum number of frames that are retained in the buffer.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of frames that are currently retained in the buffer.
+    pub fn len(&self) -> usize {
+        self.history_len
+    }
+
+    /// Returns true if no frames are retained in the buffer.
+    pub fn is_empty(&self) -> bool {
+        self.history_len == 0
+    }
+
+    /// Returns the input position of the oldest retained frame.
+    pub fn start_position(&self) -> usize {
+        self.end_position - self.history_len
+    }
+
+    /// Returns the input position after the newest retained frame, which is the total number of
+    /// frames pushed so far.
+    pub fn end_position(&self) -> usize {
+        self.end_position
+    }
+
+    /// Returns true if all input frames which a grain at the given request's position may read
+    /// have been pushed, which is the case when the stretcher's max input frame count of frames
+    /// after the position are available.
+    pub fn covers<E: EditionMarker>(&self, stretcher: &Stretcher<E>, request: &Request) -> bool {
+        let margin = stretcher.max_input_frame_count() as f64;
+        request.position + margin <= self.end_position as f64
+    }
+
+    /// Drops all retained frames. The input position continues at [`Self::end_position`].
+    pub fn clear(&mut self) {
+        self.history_len = 0;
+    }
+
+    /// Appends planar input audio, dropping the oldest frames when the buffer is full.
+    ///
+    /// # Errors
+    /// Returns an error if the number of channels doesn't match the buffer's channel count, or
+    /// if channels have different lengths.
+    pub fn push<I: AsRef<[f32]>>(&mut self, input: &[I]) -> Result<(), Error> {
+        realtime::guard(|| {
+            if input.len() != self.channels.len() {
+                return Err(Error::ChannelMismatch {
+                    expected: self.channels.len(),
+                    actual: input.len(),
+                });
+            }
+            let frame_count = input.first().map_or(0, |c| c.as_ref().len());
+            for (channel, samples) in input.iter().enumerate() {
+                let len = samples.as_ref().len();
+                if len != frame_count {
+                    return Err(Error::ChannelLengthMismatch {
+                        channel,
+                        len,
+                        expected: frame_count,
+                    });
+                }
+            }
+
+            // only the newest `capacity` frames survive the push
+            let skip = frame_count.saturating_sub(self.capacity);
+            let write_pos = (self.end_position + skip) % self.capacity;
+            for (buffer, samples) in self.channels.iter_mut().zip(input) {
+                let samples = &samples.as_ref()[skip..];
+                let (head, tail) = samples.split_at(samples.len().min(self.capacity - write_pos));
+                buffer[write_pos..write_pos + head.len()].copy_from_slice(head);
+                buffer[..tail.len()].copy_from_slice(tail);
+            }
+            self.end_position += frame_count;
+            self.history_len = (self.history_len + frame_count).min(self.capacity);
+            Ok(())
+        })
+    }
+
+    /// Specifies the grain for the given request and analyses it with the retained input audio,
+    /// muting all parts of the grain which lie outside of the retained history.
+    ///
+    /// Returns the grain's input chunk in input frames, relative to the start of the input
+    /// (not the buffer). When the chunk's end exceeds [`Self::end_position`], the grain was
+    /// rendered with missing input: use [`Self::covers`] to wait for more input first.
+    ///
+    /// # Errors
+    /// Returns an error if the request is invalid, or if the stretcher's channel count doesn't
+    /// match the buffer's channel count.
+    pub fn analyse<E: EditionMarker>(
+        &mut self,
+        stretcher: &mut Stretcher<E>,
+        request: &Request,
+    ) -> Result<InputChunk, Error> {
+        realtime::guard(|| {
+            if stretcher.num_channels() != self.channels.len() {
+                return Err(Error::ChannelMismatch {
+                    expected: stretcher.num_channels(),
+                    actual: self.channels.len(),
+                });
+            }
+            let start_position = self.start_position();
+            let chunk = stretcher.try_specify_grain_in_buffer(request, start_position as f64)?;
+
+            let mute = chunk.mute_range(self.history_len);
+            let begin = (chunk.begin + mute.head as isize) as usize;
+            let end = (chunk.end - mute.tail as isize) as usize;
+            if end > begin {
+                self.read(begin, end, mute.head);
+            }
+            stretcher.try_analyse_grain_with_mute(&self.scratch, self.scratch_stride, mute)?;
+
+            Ok(InputChunk {
+                begin: chunk.begin + start_position as isize,
+                end: chunk.end + start_position as isize,
+            })
+        })
+    }
+
+    /// Copies retained frames `begin..end`, relative to the oldest retained frame, into the
+    /// scratch buffer at the given offset.
+    fn read(&mut self, begin: usize, end: usize, offset: usize) {
+        let read_pos = (self.end_position - self.history_len + begin) % self.capacity;
+        let len = end - begin;
+        let head_len = len.min(self.capacity - read_pos);
+        for (buffer, scratch) in self
+            .channels
+            .iter()
+            .zip(self.scratch.chunks_exact_mut(self.scratch_stride))
+        {
+            let scratch = &mut scratch[offset..offset + len];
+            scratch[..head_len].copy_from_slice(&buffer[read_pos..read_pos + head_len]);
+            scratch[head_len..].copy_from_slice(&buffer[..len - head_len]);
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_signals::{sine, SAMPLE_RATE};
+
+    #[test]
+    fn input_buffer_wraps() {
+        let stretcher = Stretcher::new(44100, 2).unwrap();
+        let capacity = stretcher.max_input_frame_count();
+        let mut buffer = GrainInputBuffer::new(&stretcher, 0);
+        assert_eq!(buffer.capacity(), capacity);
+        assert!(buffer.is_empty());
+
+        // push a ramp in odd sized blocks, so the ring wraps at arbitrary offsets
+        let ramp = (0..capacity * 3).map(|i| i as f32).collect::<Vec<_>>();
+        for block in ramp.chunks(capacity / 3 + 7) {
+            let negated = block.iter().map(|s| -s).collect::<Vec<_>>();
+            buffer.push(&[block, &negated]).unwrap();
+
+            assert_eq!(buffer.end_position(), block[block.len() - 1] as usize + 1);
+            assert_eq!(buffer.len(), buffer.end_position().min(capacity));
+            let start = buffer.start_position();
+            buffer.read(0, buffer.len(), 0);
+            for (frame, position) in (start..buffer.end_position()).enumerate() {
+                assert_eq!(buffer.scratch[frame], position as f32);
+                assert_eq!(
+                    buffer.scratch[buffer.scratch_stride + frame],
+                    -(position as f32)
+                );
+            }
+        }
+
+        // oversized pushes only keep the newest frames
+        buffer.push(&[&ramp, &ramp]).unwrap();
+        assert_eq!(buffer.len(), capacity);
+        buffer.read(capacity - 1, capacity, 0);
+        assert_eq!(buffer.scratch[0], ramp[ramp.len() - 1]);
+
+        assert!(matches!(
+            buffer.push(&[&ramp]),
+            Err(Error::ChannelMismatch { .. })
+        ));
+        assert!(matches!(
+            buffer.push(&[&ramp, &ramp[1..]]),
+            Err(Error::ChannelLengthMismatch { channel: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn input_buffer_live_stretch() {
+        let mut stretcher = Stretcher::new(SAMPLE_RATE, 1).unwrap();
+        let mut buffer = GrainInputBuffer::new(&stretcher, SAMPLE_RATE);
+
+        let input = sine(440.0, 1.0, SAMPLE_RATE * 4);
+
+        let mut request = Request {
+            pitch: 1.0,
+            speed: 0.75,
+            position: 0.0,
+            reset: true,
+        };
+        stretcher.preroll(&mut request);
+
+        let mut output_len = 0;
+        for block in input.chunks(512) {
+            buffer.push(&[block]).unwrap();
+            // render grains as long as their input is available
+            while buffer.covers(&stretcher, &request) {
+                let chunk = buffer.analyse(&mut stretcher, &request).unwrap();
+                assert!(chunk.end <= buffer.end_position() as isize);
+                let output = stretcher.synthesise_grain();
+                assert!(output.channel(0).iter().all(|s| s.is_finite()));
+                output_len += output.frame_count;
+                stretcher.next(&mut request);
+            }
+        }
+        assert!(buffer.start_position() > 0);
+        assert!(request.position > (SAMPLE_RATE * 3) as f64);
+        assert!(output_len > SAMPLE_RATE * 3);
+    }
+}
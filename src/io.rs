@@ -0,0 +1,521 @@
+//! Audio file decoding via [symphonia](https://docs.rs/symphonia), for WAV, FLAC, Ogg Vorbis
+//! and MP3 files.
+//!
+//! [`decode_file`] decodes a whole file into memory, e.g. for the [`offline`](crate::offline)
+//! stretcher or a [`Player`](crate::player::Player). [`FileDecoder`] decodes files block by
+//! block, and [`stretch_file`] streams a file through a [`Stream`] with constant memory usage,
+//! so files of arbitrary length can be stretched.
+
+use std::{fmt, fs::File, path::Path, time::Duration};
+
+use symphonia::core::{
+    audio::{AudioBuffer, Signal},
+    codecs::{Decoder, DecoderOptions, CODEC_TYPE_NULL},
+    errors::Error as SymphoniaError,
+    formats::{FormatOptions, FormatReader},
+    io::MediaSourceStream,
+    meta::MetadataOptions,
+    probe::Hint,
+};
+
+use crate::{source::AudioSource, StreamBuilder};
+
+// -------------------------------------------------------------------------------------------------
+
+/// Errors reported while decoding and stretching audio files.
+#[derive(Debug)]
+pub enum DecodeError {
+    /// The file could not be read.
+    Io(std::io::Error),
+    /// The file's container format or codec is not supported.
+    Unsupported(String),
+    /// The file is malformed and could not be decoded.
+    Malformed(String),
+    /// The number of channel buffers passed to [`FileDecoder::read`] doesn't match the file's
+    /// channel count.
+    ChannelMismatch { expected: usize, actual: usize },
+    /// Stretching the decoded audio failed.
+    Stretch(crate::Error),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::Io(err) => write!(f, "failed to read audio file: {err}"),
+            DecodeError::Unsupported(what) => write!(f, "unsupported audio file: {what}"),
+            DecodeError::Malformed(what) => write!(f, "malformed audio file: {what}"),
+            DecodeError::ChannelMismatch { expected, actual } => write!(
+                f,
+                "channel count mismatch: got {actual} channels, but audio file has {expected}"
+            ),
+            DecodeError::Stretch(err) => write!(f, "failed to stretch audio file: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DecodeError::Io(err) => Some(err),
+            DecodeError::Stretch(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for DecodeError {
+    fn from(err: std::io::Error) -> Self {
+        DecodeError::Io(err)
+    }
+}
+
+impl From<crate::Error> for DecodeError {
+    fn from(err: crate::Error) -> Self {
+        DecodeError::Stretch(err)
+    }
+}
+
+impl From<SymphoniaError> for DecodeError {
+    fn from(err: SymphoniaError) -> Self {
+        match err {
+            SymphoniaError::IoError(err) => DecodeError::Io(err),
+            SymphoniaError::Unsupported(what) => DecodeError::Unsupported(what.to_string()),
+            err => DecodeError::Malformed(err.to_string()),
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+/// Decoded, planar audio with its sample rate.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlanarAudio {
+    /// Samples of each channel, which all have the same length.
+    pub channels: Vec<Vec<f32>>,
+    /// Sample rate in Hz.
+    pub sample_rate: usize,
+}
+
+impl PlanarAudio {
+    /// Returns the number of channels.
+    pub fn num_channels(&self) -> usize {
+        self.channels.len()
+    }
+
+    /// Returns the number of frames.
+    pub fn frame_count(&self) -> usize {
+        self.channels.first().map_or(0, Vec::len)
+    }
+
+    /// Returns the duration of the audio.
+    pub fn duration(&self) -> Duration {
+        if self.sample_rate == 0 {
+            return Duration::ZERO;
+        }
+        Duration::from_secs_f64(self.frame_count() as f64 / self.sample_rate as f64)
+    }
+}
+
+impl AudioSource for PlanarAudio {
+    fn len(&self) -> usize {
+        self.frame_count()
+    }
+
+    fn channels(&self) -> usize {
+        self.num_channels()
+    }
+
+    fn read(&mut self, begin: usize, end: usize, output: &mut [f32], channel_stride: usize) {
+        self.channels.read(begin, end, output, channel_stride)
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+/// Decodes the first audio track of a WAV, FLAC, Ogg Vorbis or MP3 file into memory.
+///
+/// ```rust, no_run
+/// use bungee_rs::{io::decode_file, offline};
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let audio = decode_file("input.flac")?;
+///     let sample_rate = audio.sample_rate;
+///     let stretched = offline::stretch_source(audio, sample_rate, 0.5, 1.0)?;
+///     Ok(())
+/// }
+/// ```
+///
+/// # Errors
+/// Returns an error if the file can't be read, or if its format is not supported or malformed.
+pub fn decode_file(path: impl AsRef<Path>) -> Result<PlanarAudio, DecodeError> {
+    let mut decoder = FileDecoder::open(path)?;
+    let mut channels = vec![Vec::new(); decoder.num_channels()];
+    if let Some(frame_count) = decoder.frame_count() {
+        channels
+            .iter_mut()
+            .for_each(|c| c.reserve_exact(frame_count));
+    }
+    let mut block = vec![vec![0.0; BLOCK_SIZE]; decoder.num_channels()];
+    loop {
+        let frame_count = decoder.read(&mut block)?;
+        if frame_count == 0 {
+            break;
+        }
+        for (channel, block) in channels.iter_mut().zip(&block) {
+            channel.extend_from_slice(&block[..frame_count]);
+        }
+    }
+    Ok(PlanarAudio {
+        channels,
+        sample_rate: decoder.sample_rate(),
+    })
+}
+
+/// Streams the first audio track of a WAV, FLAC, Ogg Vorbis or MP3 file through a latency
+/// compensated [`Stream`](crate::Stream), without decoding the whole file into memory.
+///
+/// `output` gets called with planar output buffers and the number of valid frames in them,
+/// whenever a block got stretched. Returns the total number of output frames, which is
+/// `round(input_len / speed)`.
+///
+/// ```rust, no_run
+/// use bungee_rs::io::stretch_file;
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let mut output_len = 0;
+///     stretch_file("podcast.mp3", 1.5, 1.0, |channels, frame_count| {
+///         // e.g. encode `channels[..][..frame_count]`
+///         output_len += frame_count;
+///     })?;
+///     Ok(())
+/// }
+/// ```
+///
+/// # Errors
+/// Returns an error if the file can't be decoded, or if speed or pitch are invalid.
+pub fn stretch_file(
+    path: impl AsRef<Path>,
+    speed: f64,
+    pitch: f64,
+    mut output: impl FnMut(&[Vec<f32>], usize),
+) -> Result<usize, DecodeError> {
+    if !(speed.is_finite() && speed > 0.0) {
+        return Err(crate::Error::InvalidSpeed(speed).into());
+    }
+    if !(pitch.is_finite() && pitch > 0.0) {
+        return Err(crate::Error::InvalidPitch(pitch).into());
+    }
+    let mut decoder = FileDecoder::open(path)?;
+    let num_channels = decoder.num_channels();
+    let mut stream = StreamBuilder::new(decoder.sample_rate(), num_channels, BLOCK_SIZE)
+        .latency_compensation(true)
+        .build()?;
+
+    let mut input_block = vec![vec![0.0; BLOCK_SIZE]; num_channels];
+    let mut output_block =
+        vec![vec![0.0; (BLOCK_SIZE as f64 / speed).ceil() as usize]; num_channels];
+    let mut output_len = 0;
+    loop {
+        let frame_count = decoder.read(&mut input_block)?;
+        if frame_count == 0 {
+            break;
+        }
+        let output_frame_count = stream.try_process(
+            Some(&input_block),
+            &mut output_block,
+            frame_count,
+            frame_count as f64 / speed,
+            pitch,
+        )?;
+        output(&output_block, output_frame_count);
+        output_len += output_frame_count;
+    }
+
+    // drain the stream's tail
+    let mut tail_block = vec![vec![0.0; stream.finish_frame_count()]; num_channels];
+    let tail_frame_count = stream.try_finish(&mut tail_block)?;
+    if tail_frame_count > 0 {
+        output(&tail_block, tail_frame_count);
+    }
+    Ok(output_len + tail_frame_count)
+}
+
+/// Number of frames which get decoded and stretched at once.
+const BLOCK_SIZE: usize = 4096;
+
+// -------------------------------------------------------------------------------------------------
+
+/// Decodes the first audio track of a WAV, FLAC, Ogg Vorbis or MP3 file block by block.
+pub struct FileDecoder {
+    format: Box<dyn FormatReader>,
+    decoder: Box<dyn Decoder>,
+    track_id: u32,
+    num_channels: usize,
+    sample_rate: usize,
+    frame_count: Option<usize>,
+    buffer: AudioBuffer<f32>,
+    buffer_pos: usize,
+}
+
+impl FileDecoder {
+    /// Opens a file and probes its format, channel layout and sample rate.
+    ///
+    /// # Errors
+    /// Returns an error if the file can't be read, or if it contains no supported audio track.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, DecodeError> {
+        let path = path.as_ref();
+        let file = File::open(path)?;
+        let stream = MediaSourceStream::new(Box::new(file), Default::default());
+
+        let mut hint = Hint::new();
+        if let Some(extension) = path.extension().and_then(|e| e.to_str()) {
+            hint.with_extension(extension);
+        }
+        let format = symphonia::default::get_probe()
+            .format(
+                &hint,
+                stream,
+                &FormatOptions::default(),
+                &MetadataOptions::default(),
+            )?
+            .format;
+
+        let track = format
+            .tracks()
+            .iter()
+            .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+            .ok_or_else(|| DecodeError::Unsupported("no audio track".to_string()))?;
+        let decoder = symphonia::default::get_codecs()
+            .make(&track.codec_params, &DecoderOptions::default())?;
+        let track_id = track.id;
+        let frame_count = track.codec_params.n_frames.map(|n| n as usize);
+
+        let mut file_decoder = Self {
+            format,
+            decoder,
+            track_id,
+            num_channels: 0,
+            sample_rate: 0,
+            frame_count,
+            buffer: AudioBuffer::unused(),
+            buffer_pos: 0,
+        };
+        // decode the first packet to get the actual signal spec
+        if !file_decoder.decode_packet()? {
+            return Err(DecodeError::Malformed("no audio frames".to_string()));
+        }
+        let spec = file_decoder.buffer.spec();
+        file_decoder.num_channels = spec.channels.count();
+        file_decoder.sample_rate = spec.rate as usize;
+        Ok(file_decoder)
+    }
+
+    /// Returns the number of channels.
+    pub fn num_channels(&self) -> usize {
+        self.num_channels
+    }
+
+    /// Returns the sample rate in Hz.
+    pub fn sample_rate(&self) -> usize {
+        self.sample_rate
+    }
+
+    /// Returns the number of frames in the track, if the file's header specifies it.
+    pub fn frame_count(&self) -> Option<usize> {
+        self.frame_count
+    }
+
+    /// Decodes the next frames into the given planar channel buffers, filling them up to the
+    /// length of the shortest one. Returns the number of decoded frames, which is only less
+    /// than the buffer length at the end of the track, and 0 after it.
+    ///
+    /// # Errors
+    /// Returns `DecodeError::ChannelMismatch` if the number of channel buffers doesn't match
+    /// the decoder's channel count, or an error if the file can't be read or decoded. Chained
+    /// Ogg streams are not supported and fail with `DecodeError::Unsupported` at the first
+    /// chained stream.
+    pub fn read<O: AsMut<[f32]>>(&mut self, output: &mut [O]) -> Result<usize, DecodeError> {
+        if output.len() != self.num_channels {
+            return Err(DecodeError::ChannelMismatch {
+                expected: self.num_channels,
+                actual: output.len(),
+            });
+        }
+        let len = output
+            .iter_mut()
+            .map(|c| c.as_mut().len())
+            .min()
+            .unwrap_or(0);
+        let mut written = 0;
+        while written < len {
+            if self.buffer_pos >= self.buffer.frames() && !self.decode_packet()? {
+                break;
+            }
+            let frame_count = (len - written).min(self.buffer.frames() - self.buffer_pos);
+            for (channel, output) in output.iter_mut().enumerate() {
+                output.as_mut()[written..written + frame_count].copy_from_slice(
+                    &self.buffer.chan(channel)[self.buffer_pos..self.buffer_pos + frame_count],
+                );
+            }
+            self.buffer_pos += frame_count;
+            written += frame_count;
+        }
+        Ok(written)
+    }
+
+    /// Decodes the next packet of the track into the decode buffer. Returns false at the end
+    /// of the track.
+    fn decode_packet(&mut self) -> Result<bool, DecodeError> {
+        loop {
+            let packet = match self.format.next_packet() {
+                Ok(packet) => packet,
+                Err(SymphoniaError::IoError(err))
+                    if err.kind() == std::io::ErrorKind::UnexpectedEof =>
+                {
+                    return Ok(false);
+                }
+                // chained streams are not supported: fail instead of truncating the track
+                Err(SymphoniaError::ResetRequired) => {
+                    return Err(DecodeError::Unsupported(
+                        "chained streams or track changes within the file".to_string(),
+                    ));
+                }
+                Err(err) => return Err(err.into()),
+            };
+            if packet.track_id() != self.track_id {
+                continue;
+            }
+            let decoded = match self.decoder.decode(&packet) {
+                Ok(decoded) => decoded,
+                // skip corrupt packets
+                Err(SymphoniaError::DecodeError(_)) => continue,
+                Err(err) => return Err(err.into()),
+            };
+            if decoded.frames() == 0 {
+                continue;
+            }
+            if self.num_channels != 0 && decoded.spec().channels.count() != self.num_channels {
+                return Err(DecodeError::Unsupported(
+                    "channel layout changes within the track".to_string(),
+                ));
+            }
+            if self.buffer.capacity() != decoded.capacity() || self.buffer.spec() != decoded.spec()
+            {
+                self.buffer = decoded.make_equivalent();
+            }
+            decoded.convert(&mut self.buffer);
+            self.buffer_pos = 0;
+            return Ok(true);
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes a 16-bit stereo PCM WAV file with a ramp in the left and a negated ramp in the
+    /// right channel.
+    fn write_wav(frame_count: usize) -> std::path::PathBuf {
+        let mut data = Vec::new();
+        for frame in 0..frame_count {
+            let sample = (frame % 256) as i16 * 64;
+            data.extend_from_slice(&sample.to_le_bytes());
+            data.extend_from_slice(&(-sample).to_le_bytes());
+        }
+        let mut wav = Vec::new();
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&(36 + data.len() as u32).to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt ");
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes()); // PCM
+        wav.extend_from_slice(&2u16.to_le_bytes()); // channels
+        wav.extend_from_slice(&44100u32.to_le_bytes());
+        wav.extend_from_slice(&(44100u32 * 4).to_le_bytes());
+        wav.extend_from_slice(&4u16.to_le_bytes());
+        wav.extend_from_slice(&16u16.to_le_bytes());
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        wav.extend_from_slice(&data);
+
+        let path = std::env::temp_dir().join(format!(
+            "bungee-rs-io-{}-{frame_count}.wav",
+            std::process::id()
+        ));
+        std::fs::write(&path, &wav).unwrap();
+        path
+    }
+
+    #[test]
+    fn decode_wav_file() {
+        const FRAME_COUNT: usize = 10000;
+        let path = write_wav(FRAME_COUNT);
+
+        let audio = decode_file(&path).unwrap();
+        assert_eq!(audio.num_channels(), 2);
+        assert_eq!(audio.sample_rate, 44100);
+        assert_eq!(audio.frame_count(), FRAME_COUNT);
+        for frame in [0, 1, 255, 256, FRAME_COUNT - 1] {
+            let expected = (frame % 256) as f32 * 64.0 / 32768.0;
+            assert_eq!(audio.channels[0][frame], expected);
+            assert_eq!(audio.channels[1][frame], -expected);
+        }
+
+        // block-wise decoding matches
+        let mut decoder = FileDecoder::open(&path).unwrap();
+        assert_eq!(decoder.frame_count(), Some(FRAME_COUNT));
+        let mut block = vec![vec![0.0; 999]; 2];
+        let mut position = 0;
+        loop {
+            let frame_count = decoder.read(&mut block).unwrap();
+            if frame_count == 0 {
+                break;
+            }
+            assert_eq!(
+                block[0][..frame_count],
+                audio.channels[0][position..position + frame_count]
+            );
+            position += frame_count;
+        }
+        assert_eq!(position, FRAME_COUNT);
+        assert!(matches!(
+            decoder.read(&mut block[..1]),
+            Err(DecodeError::ChannelMismatch {
+                expected: 2,
+                actual: 1
+            })
+        ));
+
+        assert!(matches!(
+            decode_file(path.with_extension("missing")),
+            Err(DecodeError::Io(_))
+        ));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn stretch_wav_file() {
+        const FRAME_COUNT: usize = 30000;
+        let path = write_wav(FRAME_COUNT);
+
+        for speed in [0.75, 2.0] {
+            let mut output_len = 0;
+            let total_len = stretch_file(&path, speed, 1.0, |channels, frame_count| {
+                assert_eq!(channels.len(), 2);
+                assert!(channels[0][..frame_count].iter().all(|s| s.is_finite()));
+                output_len += frame_count;
+            })
+            .unwrap();
+            assert_eq!(total_len, output_len);
+            assert_eq!(total_len, (FRAME_COUNT as f64 / speed).round() as usize);
+        }
+
+        assert!(matches!(
+            stretch_file(&path, 0.0, 1.0, |_, _| {}),
+            Err(DecodeError::Stretch(crate::Error::InvalidSpeed(_)))
+        ));
+        std::fs::remove_file(path).unwrap();
+    }
+}
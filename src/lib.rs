@@ -22,6 +22,22 @@ pub struct Request {
     pub reset: bool,
 }
 
+impl Request {
+    /// Validates the request's pitch, speed and position.
+    pub(crate) fn validate(&self) -> Result<(), Error> {
+        if !(self.pitch.is_finite() && self.pitch > 0.0) {
+            return Err(Error::InvalidPitch(self.pitch));
+        }
+        if !self.speed.is_finite() {
+            return Err(Error::InvalidSpeed(self.speed));
+        }
+        if self.position.is_infinite() {
+            return Err(Error::NonFinitePosition(self.position));
+        }
+        Ok(())
+    }
+}
+
 impl From<bungee_sys::Request> for Request {
     fn from(ffi: bungee_sys::Request) -> Self {
         Request {
@@ -62,6 +78,17 @@ impl InputChunk {
     pub fn len(&self) -> usize {
         (self.end - self.begin).max(0) as usize
     }
+
+    /// Returns the number of frames at the start and end of the chunk which lie outside of an
+    /// input source with `source_len` frames, and thus should be muted when analysing the grain.
+    pub fn mute_range(&self, source_len: usize) -> MuteRange {
+        let source_len = source_len as isize;
+        let begin = self.begin.clamp(0, source_len);
+        let end = self.end.clamp(begin, source_len);
+        let head = ((begin - self.begin).max(0) as usize).min(self.len());
+        let tail = self.len() - head - (end - begin) as usize;
+        MuteRange { head, tail }
+    }
 }
 
 impl From<bungee_sys::InputChunk> for InputChunk {
@@ -84,11 +111,28 @@ impl From<InputChunk> for bungee_sys::InputChunk {
 
 // -------------------------------------------------------------------------------------------------
 
-/// A safe wrapper around the FFI OutputChunk struct.
+/// Number of frames at the start and end of an input chunk which get muted when analysing a
+/// grain, e.g. because they lie before the start or after the end of the input audio.
+///
+/// Muted frames don't need to be filled in by the caller: their content is ignored.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MuteRange {
+    /// Number of frames to mute at the start of the input chunk.
+    pub head: usize,
+    /// Number of frames to mute at the end of the input chunk.
+    pub tail: usize,
+}
+
+// -------------------------------------------------------------------------------------------------
+
+/// A safe view of the FFI OutputChunk struct.
+///
+/// The audio data is owned by the stretcher that produced the chunk and stays valid until the
+/// stretcher is used again, which the borrow checker enforces via the chunk's lifetime.
 #[derive(Debug)]
 pub struct OutputChunk<'a> {
     /// Audio output data, not aligned and not interleaved.
-    pub data: &'a mut [f32],
+    pub data: &'a [f32],
     /// Number of frames in the output data.
     pub frame_count: usize,
     /// The nth audio channel audio starts at `data[n * channelStride]`.
@@ -99,85 +143,243 @@ pub struct OutputChunk<'a> {
 }
 
 impl<'a> OutputChunk<'a> {
-    /// Create a new OutputChunk with the provided data slice.
-    pub fn new(data: &'a mut [f32], channel_stride: usize) -> Self {
-        OutputChunk {
-            data,
-            frame_count: 0,
-            channel_stride,
-            request: [None, None],
+    /// Returns the number of audio channels in the output data.
+    pub fn num_channels(&self) -> usize {
+        if self.frame_count == 0 {
+            0
+        } else {
+            (self.data.len() - self.frame_count)
+                .checked_div(self.channel_stride)
+                .map_or(1, |n| n + 1)
         }
     }
-}
 
-impl<'a> From<bungee_sys::OutputChunk> for OutputChunk<'a> {
-    fn from(ffi: bungee_sys::OutputChunk) -> Self {
-        // Safety: We're creating a slice from the raw pointer, but we don't know its length
-        // This is why we need to rely on frame_count and channel_stride to determine the actual data
-        let data_len = if ffi.frame_count > 0 && ffi.channel_stride > 0 {
-            ffi.frame_count as usize * ffi.channel_stride as usize
+    /// Returns the `frame_count` output frames of the given channel.
+    ///
+    /// # Panics
+    /// Panics if the channel index is out of range.
+    pub fn channel(&self, channel: usize) -> &'a [f32] {
+        let start = channel * self.channel_stride;
+        &self.data[start..start + self.frame_count]
+    }
+
+    /// Creates an output view from a chunk that got filled in by the FFI stretcher, copying
+    /// the chunk's requests.
+    ///
+    /// # Safety
+    /// `ffi.data` must either be null or point to at least `num_channels` channels of
+    /// `ffi.frame_count` frames, spaced `ffi.channel_stride` frames apart, which stay valid and
+    /// unmodified for the lifetime `'a`. `ffi.request` pointers must be null or valid for reads.
+    pub(crate) unsafe fn from_ffi(ffi: &bungee_sys::OutputChunk, num_channels: usize) -> Self {
+        let frame_count = ffi.frame_count.max(0) as usize;
+        let channel_stride = ffi.channel_stride.max(0) as usize;
+
+        let data_len = if frame_count > 0 && num_channels > 0 {
+            (num_channels - 1) * channel_stride + frame_count
         } else {
             0
         };
-
-        // Safety: We're assuming the raw pointer points to valid memory
-        // This is safe because the FFI function will have allocated this memory
-        let data_slice = if data_len > 0 && !ffi.data.is_null() {
-            unsafe { std::slice::from_raw_parts_mut(ffi.data, data_len) }
+        let data: &'a [f32] = if data_len > 0 && !ffi.data.is_null() {
+            std::slice::from_raw_parts(ffi.data, data_len)
         } else {
-            &mut []
+            &[]
+        };
+        let frame_count = if data.is_empty() { 0 } else { frame_count };
+
+        let request = |ptr: *const bungee_sys::Request| {
+            if ptr.is_null() {
+                None
+            } else {
+                Some(Request::from(*ptr))
+            }
         };
 
         OutputChunk {
-            data: data_slice,
-            frame_count: ffi.frame_count as usize,
-            channel_stride: ffi.channel_stride as usize,
-            request: [
-                if ffi.request[0].is_null() {
-                    None
-                } else {
-                    Some(unsafe { *ffi.request[0] }.into())
-                },
-                if ffi.request[1].is_null() {
-                    None
-                } else {
-                    Some(unsafe { *ffi.request[1] }.into())
-                },
-            ],
+            data,
+            frame_count,
+            channel_stride,
+            request: [request(ffi.request[0]), request(ffi.request[1])],
         }
     }
 }
 
-impl<'a> From<&mut OutputChunk<'a>> for bungee_sys::OutputChunk {
-    fn from(oc: &mut OutputChunk<'a>) -> bungee_sys::OutputChunk {
+// -------------------------------------------------------------------------------------------------
+
+mod error;
+pub use error::Error;
+
+mod info;
+pub use info::{build_info, edition, version, BuildInfo, Edition};
+
+mod control;
+pub use control::StreamController;
+
+mod stream;
+pub use stream::{Stream, StreamBuilder};
+
+mod stretcher;
+pub use stretcher::{Stretcher, StretcherBuilder};
+
+mod grain;
+
+#[cfg(feature = "analysis")]
+pub mod analysis;
+pub mod edition;
+pub mod envelope;
+pub mod input_buffer;
+#[cfg(feature = "symphonia")]
+pub mod io;
+pub mod offline;
+pub mod player;
+pub mod realtime;
+#[cfg(feature = "rodio")]
+pub mod rodio;
+pub mod source;
+pub mod tempo;
+pub mod warp;
+
+#[cfg(test)]
+mod test_alloc;
+#[cfg(test)]
+mod test_quality;
+#[cfg(test)]
+mod test_signals;
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    //! Tests for the FFI conversion paths which don't call into Bungee, so they also run with
+    //! `cargo miri test output_chunk` and `cargo miri test request`.
+
+    use super::*;
+
+    /// Mimics Bungee's `synthesiseGrain`: fills in the chunk with pointers to internal state.
+    struct MockSynthesiser {
+        data: Vec<f32>,
+        requests: [bungee_sys::Request; 2],
+        frame_count: usize,
+        channel_stride: usize,
+    }
+
+    impl MockSynthesiser {
+        fn new(num_channels: usize, frame_count: usize, channel_stride: usize) -> Self {
+            let data = (0..(num_channels - 1) * channel_stride + frame_count)
+                .map(|i| i as f32)
+                .collect();
+            let request = |position| bungee_sys::Request {
+                position,
+                speed: 0.5,
+                pitch: 2.0,
+                reset: 0,
+            };
+            Self {
+                data,
+                requests: [request(100.0), request(100.0 + frame_count as f64 * 0.5)],
+                frame_count,
+                channel_stride,
+            }
+        }
+
+        fn synthesise(&mut self, ffi: &mut bungee_sys::OutputChunk) {
+            ffi.data = self.data.as_mut_ptr();
+            ffi.frame_count = self.frame_count as i32;
+            ffi.channel_stride = self.channel_stride as isize;
+            ffi.request = [&self.requests[0], &self.requests[1]];
+        }
+
+        fn synthesise_grain(&mut self, num_channels: usize) -> OutputChunk<'_> {
+            let mut ffi = empty_ffi_chunk();
+            self.synthesise(&mut ffi);
+            unsafe { OutputChunk::from_ffi(&ffi, num_channels) }
+        }
+    }
+
+    fn empty_ffi_chunk() -> bungee_sys::OutputChunk {
         bungee_sys::OutputChunk {
-            data: if oc.data.is_empty() {
-                std::ptr::null_mut()
-            } else {
-                oc.data.as_ptr() as *mut _
-            },
-            frame_count: oc.frame_count as i32,
-            channel_stride: oc.channel_stride as isize,
-            request: [
-                if let Some(req) = oc.request[0] {
-                    &req.into() as *const _
-                } else {
-                    std::ptr::null()
-                },
-                if let Some(req) = oc.request[1] {
-                    &req.into() as *const _
-                } else {
-                    std::ptr::null()
-                },
-            ],
+            data: std::ptr::null_mut(),
+            frame_count: 0,
+            channel_stride: 0,
+            request: [std::ptr::null(), std::ptr::null()],
         }
     }
-}
 
-// -------------------------------------------------------------------------------------------------
+    #[test]
+    fn output_chunk_from_ffi() {
+        let mut mock = MockSynthesiser::new(3, 4, 10);
+        let chunk = mock.synthesise_grain(3);
 
-mod stream;
-pub use stream::Stream;
+        assert_eq!(chunk.frame_count, 4);
+        assert_eq!(chunk.channel_stride, 10);
+        assert_eq!(chunk.num_channels(), 3);
+        assert_eq!(chunk.data.len(), 24);
+        assert_eq!(chunk.channel(0), &[0.0, 1.0, 2.0, 3.0]);
+        assert_eq!(chunk.channel(2), &[20.0, 21.0, 22.0, 23.0]);
 
-mod stretcher;
-pub use stretcher::Stretcher;
+        let [first, last] = chunk.request;
+        let (first, last) = (first.unwrap(), last.unwrap());
+        assert_eq!(first.position, 100.0);
+        assert_eq!(last.position, 102.0);
+        assert_eq!(first.pitch, 2.0);
+        assert!(!first.reset);
+    }
+
+    #[test]
+    fn output_chunk_requests_are_owned() {
+        let mut mock = MockSynthesiser::new(1, 4, 4);
+        let request = mock.synthesise_grain(1).request[0];
+
+        // mutating the backend's state must not affect copies of its requests
+        mock.requests[0].position = -1.0;
+        assert_eq!(request.unwrap().position, 100.0);
+    }
+
+    #[test]
+    fn output_chunk_from_empty_ffi() {
+        let chunk = unsafe { OutputChunk::from_ffi(&empty_ffi_chunk(), 2) };
+        assert!(chunk.data.is_empty());
+        assert_eq!(chunk.frame_count, 0);
+        assert_eq!(chunk.num_channels(), 0);
+        assert!(chunk.request.iter().all(Option::is_none));
+
+        // a frame count without data is treated as empty
+        let ffi = bungee_sys::OutputChunk {
+            frame_count: 16,
+            channel_stride: 16,
+            ..empty_ffi_chunk()
+        };
+        let chunk = unsafe { OutputChunk::from_ffi(&ffi, 2) };
+        assert!(chunk.data.is_empty());
+        assert_eq!(chunk.frame_count, 0);
+    }
+
+    #[test]
+    fn request_ffi_roundtrip() {
+        let request = Request {
+            position: 12.5,
+            speed: -1.0,
+            pitch: 0.5,
+            reset: true,
+        };
+        let ffi = bungee_sys::Request::from(request);
+        assert_eq!(ffi.reset, 1);
+        let back = Request::from(ffi);
+        assert_eq!(back.position, request.position);
+        assert_eq!(back.speed, request.speed);
+        assert_eq!(back.pitch, request.pitch);
+        assert!(back.reset);
+
+        let chunk = InputChunk::from(bungee_sys::InputChunk {
+            begin: -10,
+            end: 20,
+        });
+        assert_eq!(chunk.len(), 30);
+        assert_eq!(
+            bungee_sys::InputChunk::from(chunk),
+            bungee_sys::InputChunk {
+                begin: -10,
+                end: 20
+            }
+        );
+    }
+}
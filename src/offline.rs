@@ -0,0 +1,471 @@
+//! Offline, whole-buffer time-stretching on top of the low-level [`Stretcher`] API.
+
+use crate::{
+    envelope::Envelope, grain::GrainInput, source::AudioSource, tempo, warp::WarpMap, Error,
+    OutputChunk, Request, Stretcher,
+};
+
+// -------------------------------------------------------------------------------------------------
+
+/// Time-stretches and pitch-shifts the given planar input audio as a whole.
+///
+/// Drives the stretcher's grain loop until the entire input has been rendered, trims the
+/// stretcher's pre-roll and returns exactly `round(input_len / speed)` output frames per channel.
+///
+/// Parameters:
+/// * **input:** Planar input audio, one slice for each channel, all of the same length
+/// * **sample_rate:** Sample rate of the input and output audio
+/// * **speed:** Output audio speed (see Request::speed): must be > 0
+/// * **pitch:** Audio pitch shift (see Request::pitch)
+///
+/// # Errors
+/// Returns an error if the speed or pitch is invalid, if channels have different lengths,
+/// or if the stretcher cannot be created.
+pub fn stretch(
+    input: &[&[f32]],
+    sample_rate: usize,
+    speed: f64,
+    pitch: f64,
+) -> Result<Vec<Vec<f32>>, Error> {
+    validate_input(input)?;
+    stretch_source(input, sample_rate, speed, pitch)
+}
+
+/// Time-stretches and pitch-shifts the given audio source as a whole, just like [`stretch`].
+///
+/// # Errors
+/// Returns an error if the speed or pitch is invalid, or if the stretcher cannot be created.
+pub fn stretch_source(
+    source: impl AudioSource,
+    sample_rate: usize,
+    speed: f64,
+    pitch: f64,
+) -> Result<Vec<Vec<f32>>, Error> {
+    if !(speed.is_finite() && speed > 0.0) {
+        return Err(Error::InvalidSpeed(speed));
+    }
+    let output_len = tempo::output_len(source.len(), speed);
+    stretch_to_len(source, sample_rate, speed, pitch, output_len)
+}
+
+/// Time-stretches the given audio source to exactly `target_len` output frames per channel,
+/// e.g. to fit a duration (see [`tempo::frames_for_duration`]).
+///
+/// `semitones` transposes the audio independently of the resulting speed (key-lock).
+///
+/// # Errors
+/// Returns an error if the transposition is invalid, or if the stretcher cannot be created.
+pub fn stretch_to_duration(
+    source: impl AudioSource,
+    sample_rate: usize,
+    target_len: usize,
+    semitones: f64,
+) -> Result<Vec<Vec<f32>>, Error> {
+    let speed = if source.is_empty() || target_len == 0 {
+        1.0
+    } else {
+        tempo::speed_for_length(source.len(), target_len)?
+    };
+    let pitch = tempo::pitch_for_semitones(semitones);
+    stretch_to_len(source, sample_rate, speed, pitch, target_len)
+}
+
+/// Time-stretches the given audio source from `source_bpm` to `target_bpm`, and returns
+/// exactly `round(input_len * source_bpm / target_bpm)` output frames per channel.
+///
+/// `semitones` transposes the audio independently of the tempo change (key-lock).
+///
+/// # Errors
+/// Returns an error if a tempo or the transposition is invalid, or if the stretcher cannot be
+/// created.
+pub fn stretch_to_bpm(
+    source: impl AudioSource,
+    sample_rate: usize,
+    source_bpm: f64,
+    target_bpm: f64,
+    semitones: f64,
+) -> Result<Vec<Vec<f32>>, Error> {
+    let speed = tempo::speed_for_bpm(source_bpm, target_bpm)?;
+    let target_len = tempo::output_len_for_bpm(source.len(), source_bpm, target_bpm)?;
+    let pitch = tempo::pitch_for_semitones(semitones);
+    stretch_to_len(source, sample_rate, speed, pitch, target_len)
+}
+
+/// Renders the given audio source with a constant speed and pitch into exactly `output_len`
+/// output frames.
+fn stretch_to_len(
+    source: impl AudioSource,
+    sample_rate: usize,
+    speed: f64,
+    pitch: f64,
+    output_len: usize,
+) -> Result<Vec<Vec<f32>>, Error> {
+    if !(pitch.is_finite() && pitch > 0.0) {
+        return Err(Error::InvalidPitch(pitch));
+    }
+    render(source, sample_rate, Some(output_len), |_, request| {
+        request.speed = speed;
+        request.pitch = pitch;
+    })
+}
+
+/// Time-stretches and pitch-shifts the given planar input audio as a whole, with speed and
+/// pitch following the given envelopes.
+///
+/// Envelopes are evaluated in output frame time for each grain, at the output time where the
+/// grain's centre will be rendered. Rendering stops when the entire input has been consumed,
+/// so the output length is the output time at which the speed envelope's integral reaches the
+/// input length.
+///
+/// Parameters:
+/// * **input:** Planar input audio, one slice for each channel, all of the same length
+/// * **sample_rate:** Sample rate of the input and output audio
+/// * **speed:** Output audio speed envelope (see Request::speed): all values must be > 0
+/// * **pitch:** Audio pitch shift envelope (see Request::pitch): all values must be > 0
+///
+/// # Errors
+/// Returns an error if a speed or pitch value is invalid, if channels have different lengths,
+/// or if the stretcher cannot be created.
+pub fn stretch_automated(
+    input: &[&[f32]],
+    sample_rate: usize,
+    speed: &Envelope,
+    pitch: &Envelope,
+) -> Result<Vec<Vec<f32>>, Error> {
+    if let Some(speed) = speed.values().find(|s| !(s.is_finite() && *s > 0.0)) {
+        return Err(Error::InvalidSpeed(speed));
+    }
+    if let Some(pitch) = pitch.values().find(|p| !(p.is_finite() && *p > 0.0)) {
+        return Err(Error::InvalidPitch(pitch));
+    }
+    validate_input(input)?;
+
+    render(input, sample_rate, None, |output_time, request| {
+        request.speed = speed.value_at(output_time);
+        request.pitch = pitch.value_at(output_time);
+    })
+}
+
+/// Time-stretches and pitch-shifts the given audio source along a warp map: each grain's input
+/// position is the warp map's source position at the grain's output time, so the source
+/// positions of the warp markers land exactly on their target positions.
+///
+/// The output starts at target position 0 and ends where the warp map places the end of the
+/// input: it is `round(warp.target_for(input_len))` frames long.
+///
+/// # Errors
+/// Returns an error if the pitch is invalid, or if the stretcher cannot be created.
+pub fn stretch_warped(
+    source: impl AudioSource,
+    sample_rate: usize,
+    warp: &WarpMap,
+    pitch: f64,
+) -> Result<Vec<Vec<f32>>, Error> {
+    if !(pitch.is_finite() && pitch > 0.0) {
+        return Err(Error::InvalidPitch(pitch));
+    }
+    let output_len = warp.target_for(source.len() as f64).max(0.0).round() as usize;
+    render(
+        source,
+        sample_rate,
+        Some(output_len),
+        |output_time, request| {
+            request.position = warp.source_for(output_time);
+            request.speed = warp.speed_at(output_time);
+            request.pitch = pitch;
+        },
+    )
+}
+
+// -------------------------------------------------------------------------------------------------
+
+/// Runs the stretcher's grain loop over the given audio source. `automate` gets called with the
+/// output frame time of the next grain to update the grain's speed and pitch, and optionally its
+/// position. The output starts at the first grain's position. When `output_len` is `None`,
+/// rendering stops as soon as the output reaches the end of the input.
+fn render(
+    mut source: impl AudioSource,
+    sample_rate: usize,
+    output_len: Option<usize>,
+    mut automate: impl FnMut(f64, &mut Request),
+) -> Result<Vec<Vec<f32>>, Error> {
+    let num_channels = source.channels();
+    let mut stretcher = Stretcher::new(sample_rate, num_channels)?;
+
+    let mut request = Request {
+        position: 0.0,
+        speed: 1.0,
+        pitch: 1.0,
+        reset: true,
+    };
+    automate(0.0, &mut request);
+    let start_position = request.position;
+    let mut output = OutputBuffer::new(num_channels, source.len(), output_len, start_position);
+    stretcher.preroll(&mut request);
+
+    // Output time of the current grain's centre: each grain advances the input position by
+    // `speed` times the synthesis hop, and the output by exactly one synthesis hop.
+    let mut output_time = (request.position - start_position) / request.speed;
+
+    let mut grain_input = GrainInput::new(&stretcher);
+    while !output.is_finished() {
+        let input_chunk = stretcher.try_specify_grain(&request)?;
+        grain_input.analyse(&mut stretcher, &mut source, input_chunk);
+        output.append(&stretcher.synthesise_grain());
+
+        let (position, speed) = (request.position, request.speed);
+        stretcher.next(&mut request);
+        output_time += (request.position - position) / speed;
+        automate(output_time, &mut request);
+    }
+
+    Ok(output.into_inner())
+}
+
+// -------------------------------------------------------------------------------------------------
+
+/// Verifies that the given planar input audio has channels of the same length.
+fn validate_input(input: &[&[f32]]) -> Result<(), Error> {
+    if input.is_empty() {
+        return Err(Error::InvalidChannelCount(0));
+    }
+    let input_len = input[0].len();
+    for (channel, samples) in input.iter().enumerate() {
+        if samples.len() != input_len {
+            return Err(Error::ChannelLengthMismatch {
+                channel,
+                len: samples.len(),
+                expected: input_len,
+            });
+        }
+    }
+    Ok(())
+}
+
+// -------------------------------------------------------------------------------------------------
+
+/// Collects planar output chunks, dropping the stretcher's pre-roll and everything that exceeds
+/// the target output length or, without a target length, the end of the input.
+struct OutputBuffer {
+    channels: Vec<Vec<f32>>,
+    input_len: usize,
+    output_len: Option<usize>,
+    start_position: f64,
+    started: bool,
+    finished: bool,
+}
+
+impl OutputBuffer {
+    fn new(
+        num_channels: usize,
+        input_len: usize,
+        output_len: Option<usize>,
+        start_position: f64,
+    ) -> Self {
+        Self {
+            channels: vec![Vec::with_capacity(output_len.unwrap_or(input_len)); num_channels],
+            input_len,
+            output_len,
+            start_position,
+            started: false,
+            finished: output_len.unwrap_or(input_len) == 0,
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    fn append(&mut self, chunk: &OutputChunk) {
+        // input position of each output frame
+        let frame_position = match chunk.request {
+            [Some(begin), Some(end)] if chunk.frame_count > 0 => {
+                let step = (end.position - begin.position) / chunk.frame_count as f64;
+                Some(move |frame: usize| begin.position + step * frame as f64)
+            }
+            _ => None,
+        };
+
+        let mut first_frame = 0;
+        if !self.started {
+            // skip pre-roll frames, which map to input positions before the start position
+            first_frame = frame_position.map_or(chunk.frame_count, |position| {
+                (0..chunk.frame_count)
+                    .find(|&frame| position(frame) >= self.start_position)
+                    .unwrap_or(chunk.frame_count)
+            });
+            self.started = first_frame < chunk.frame_count;
+        }
+
+        let mut last_frame = chunk.frame_count;
+        if let Some(output_len) = self.output_len {
+            let remaining = output_len - self.channels[0].len();
+            last_frame = last_frame.min(first_frame + remaining);
+        } else if let Some(position) = frame_position {
+            // skip frames which map to input positions after the end of the input
+            last_frame = (first_frame..chunk.frame_count)
+                .find(|&frame| position(frame) >= self.input_len as f64)
+                .unwrap_or(chunk.frame_count);
+        }
+        self.finished = last_frame < chunk.frame_count
+            || self.output_len == Some(self.channels[0].len() + last_frame - first_frame);
+
+        for (channel, samples) in self.channels.iter_mut().enumerate() {
+            samples.extend_from_slice(&chunk.channel(channel)[first_frame..last_frame]);
+        }
+    }
+
+    fn into_inner(self) -> Vec<Vec<f32>> {
+        self.channels
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::envelope::Curve;
+    use crate::test_signals::{sine, SAMPLE_RATE};
+
+    #[test]
+    fn offline_stretch() {
+        let input = sine(440.0, 1.0, SAMPLE_RATE);
+
+        for speed in [0.5, 0.75, 1.0, 1.3, 2.0] {
+            let output = stretch(&[&input, &input], SAMPLE_RATE, speed, 1.0).unwrap();
+            let expected_len = (input.len() as f64 / speed).round() as usize;
+            assert_eq!(output.len(), 2);
+            assert!(output.iter().all(|c| c.len() == expected_len));
+            assert!(output.iter().flatten().all(|s| s.is_finite()));
+        }
+
+        assert!(matches!(
+            stretch(&[&input], SAMPLE_RATE, 0.0, 1.0),
+            Err(Error::InvalidSpeed(_))
+        ));
+        assert!(matches!(
+            stretch(&[&input, &input[1..]], SAMPLE_RATE, 1.0, 1.0),
+            Err(Error::ChannelLengthMismatch { channel: 1, .. })
+        ));
+        assert!(matches!(
+            stretch(&[&input[1..], &input], SAMPLE_RATE, 1.0, 1.0),
+            Err(Error::ChannelLengthMismatch {
+                channel: 1,
+                len: SAMPLE_RATE,
+                expected: 44099
+            })
+        ));
+        assert!(stretch(&[&[]], SAMPLE_RATE, 1.0, 1.0).unwrap()[0].is_empty());
+    }
+
+    #[test]
+    fn offline_stretch_to_target() {
+        let input = sine(440.0, 1.0, SAMPLE_RATE + 17);
+        let source: &[&[f32]] = &[&input];
+
+        // exact durations, also for lengths which aren't reachable by rounding a speed ratio
+        for target_len in [1, 1000, 30001, SAMPLE_RATE, 2 * SAMPLE_RATE + 3] {
+            let output = stretch_to_duration(source, SAMPLE_RATE, target_len, -3.5).unwrap();
+            assert_eq!(output[0].len(), target_len);
+            assert!(output[0].iter().all(|s| s.is_finite()));
+        }
+        assert!(stretch_to_duration(source, SAMPLE_RATE, 0, 0.0).unwrap()[0].is_empty());
+        let empty: &[f32] = &[];
+        assert_eq!(
+            stretch_to_duration(&[empty][..], SAMPLE_RATE, 100, 0.0).unwrap()[0].len(),
+            100
+        );
+
+        // exact tempo changes
+        for (source_bpm, target_bpm) in [(120.0, 128.0), (174.0, 87.0), (93.5, 100.0)] {
+            let output = stretch_to_bpm(source, SAMPLE_RATE, source_bpm, target_bpm, 2.0).unwrap();
+            let expected_len = tempo::output_len_for_bpm(input.len(), source_bpm, target_bpm);
+            assert_eq!(output[0].len(), expected_len.unwrap());
+        }
+        assert!(matches!(
+            stretch_to_bpm(source, SAMPLE_RATE, 120.0, -1.0, 0.0),
+            Err(Error::InvalidTempo(_))
+        ));
+        assert!(matches!(
+            stretch_to_duration(source, SAMPLE_RATE, 100, f64::NAN),
+            Err(Error::InvalidPitch(_))
+        ));
+    }
+
+    #[test]
+    fn offline_stretch_automated() {
+        let input = sine(440.0, 1.0, SAMPLE_RATE * 2);
+
+        // constant envelopes render just like a constant speed
+        let output = stretch_automated(
+            &[&input],
+            SAMPLE_RATE,
+            &Envelope::new(0.5),
+            &Envelope::new(1.0),
+        )
+        .unwrap();
+        let expected_len = input.len() * 2;
+        assert!(output[0].len().abs_diff(expected_len) < 1024);
+
+        // tape stop: speed ramps down to 0.25 within one second, then stays there.
+        // the first second of output consumes (1.0 + 0.25) / 2 seconds of input.
+        let speed = Envelope::new(1.0)
+            .with_point(SAMPLE_RATE as f64, 0.25, Curve::Linear)
+            .unwrap();
+        let pitch = Envelope::new(1.0)
+            .with_point(SAMPLE_RATE as f64, 0.25, Curve::Exponential)
+            .unwrap();
+        let output = stretch_automated(&[&input], SAMPLE_RATE, &speed, &pitch).unwrap();
+        let remaining_input = input.len() as f64 - SAMPLE_RATE as f64 * 0.625;
+        let expected_len = (SAMPLE_RATE as f64 + remaining_input / 0.25) as usize;
+        assert!(output[0].len().abs_diff(expected_len) < 2048);
+        assert!(output[0].iter().all(|s| s.is_finite()));
+
+        assert!(matches!(
+            stretch_automated(
+                &[&input],
+                SAMPLE_RATE,
+                &Envelope::new(1.0)
+                    .with_point(100.0, 0.0, Curve::Linear)
+                    .unwrap(),
+                &Envelope::new(1.0)
+            ),
+            Err(Error::InvalidSpeed(_))
+        ));
+    }
+
+    #[test]
+    fn offline_stretch_warped() {
+        let input = sine(440.0, 1.0, SAMPLE_RATE);
+        let source: &[&[f32]] = &[&input];
+
+        // the identity map renders the input unchanged in length
+        let output = stretch_warped(source, SAMPLE_RATE, &WarpMap::new(), 1.0).unwrap();
+        assert_eq!(output[0].len(), input.len());
+
+        // the output ends where the warp map places the end of the input
+        let mut warp = WarpMap::new();
+        warp.insert(0.0, 0.0).unwrap();
+        warp.insert(10000.0, 20000.0).unwrap();
+        warp.insert(30000.0, 25000.0).unwrap();
+        let output = stretch_warped(source, SAMPLE_RATE, &warp, 1.5).unwrap();
+        assert_eq!(output[0].len(), 28525);
+        assert!(output[0].iter().all(|s| s.is_finite()));
+
+        // warp maps which don't start at source position 0 skip or pad the input's start
+        let mut warp = WarpMap::new();
+        warp.insert(5000.0, 0.0).unwrap();
+        let output = stretch_warped(source, SAMPLE_RATE, &warp, 1.0).unwrap();
+        assert_eq!(output[0].len(), input.len() - 5000);
+        let mut warp = WarpMap::new();
+        warp.insert(0.0, 5000.0).unwrap();
+        let output = stretch_warped(source, SAMPLE_RATE, &warp, 1.0).unwrap();
+        assert_eq!(output[0].len(), input.len() + 5000);
+
+        assert!(matches!(
+            stretch_warped(source, SAMPLE_RATE, &WarpMap::new(), 0.0),
+            Err(Error::InvalidPitch(_))
+        ));
+    }
+}
@@ -0,0 +1,908 @@
+//! Real-time playback driver over the low-level [`Stretcher`] API, which supports seeking,
+//! scrubbing, reverse and bidirectional playback.
+
+use std::ops::Range;
+
+use crate::{grain::GrainInput, source::AudioSource, Error, OutputChunk, Request, Stretcher};
+
+// -------------------------------------------------------------------------------------------------
+
+/// Defines what happens when the playback reaches the end of the input or loop range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LoopMode {
+    /// Play until the end (or start, when playing backwards) of the input is reached.
+    #[default]
+    Off,
+    /// Jump to the opposite end of the loop range when reaching the end of the loop range.
+    /// The stretcher keeps running across the jump, so overlapping grains crossfade the loop
+    /// point instead of cutting it.
+    Loop,
+    /// Reverse the playback direction when reaching the start or end of the loop range.
+    PingPong,
+}
+
+// -------------------------------------------------------------------------------------------------
+
+/// Plays back planar input audio with the [`Stretcher`], rendering output blocks of any size.
+///
+/// Speed may be negative for reverse playback. Speed and pitch changes, including direction
+/// changes, apply smoothly with the next grain. Seeks restart the stretcher at the new position.
+/// In scrub mode, the position follows an externally driven cursor instead.
+///
+/// The input audio is read from an [`AudioSource`], so the player may own its audio
+/// (`Vec<Vec<f32>>`), share it (`Vec<Arc<[f32]>>`), borrow it (`&[&[f32]]`) or read it from
+/// any other source.
+///
+/// ```rust, no_run
+/// use bungee_rs::player::{LoopMode, Player};
+///
+/// let input = vec![vec![0.0f32; 44100]; 2];
+/// let mut player = Player::new(input, 44100)?;
+/// player.set_speed(-0.5)?; // half speed, backwards from the end
+/// player.set_loop_mode(LoopMode::PingPong);
+///
+/// let mut output = vec![vec![0.0f32; 512]; 2];
+/// while !player.is_finished() {
+///     player.process(&mut output);
+///     // use output...
+///     # break;
+/// }
+/// # Ok::<(), bungee_rs::Error>(())
+/// ```
+pub struct Player<S = Vec<Vec<f32>>> {
+    playback: Playback<S>,
+    request: Request,
+    speed: f64,
+    pitch: f64,
+    loop_mode: LoopMode,
+    loop_range: Range<usize>,
+    scrub: Option<Scrub>,
+    restart: Option<f64>,
+    started: bool,
+}
+
+impl<S: AudioSource> Player<S> {
+    /// Creates a new player for the given audio source, which starts playing at normal
+    /// speed and pitch without looping.
+    ///
+    /// # Errors
+    /// Returns an error if the stretcher cannot be created.
+    pub fn new(source: S, sample_rate: usize) -> Result<Self, Error> {
+        let playback = Playback::new(source, sample_rate)?;
+        let input_len = playback.input_len();
+        Ok(Self {
+            playback,
+            request: IDLE_REQUEST,
+            speed: 1.0,
+            pitch: 1.0,
+            loop_mode: LoopMode::Off,
+            loop_range: 0..input_len,
+            scrub: None,
+            restart: None,
+            started: false,
+        })
+    }
+
+    /// Returns the player's sample rate.
+    pub fn sample_rate(&self) -> usize {
+        self.playback.sample_rate()
+    }
+
+    /// Returns the player's channel count.
+    pub fn num_channels(&self) -> usize {
+        self.playback.num_channels()
+    }
+
+    /// Returns the player's audio source.
+    pub fn source(&self) -> &S {
+        self.playback.source()
+    }
+
+    /// Returns the number of frames in the input audio.
+    pub fn input_len(&self) -> usize {
+        self.playback.input_len()
+    }
+
+    /// Returns the current playback speed. Negative speeds play backwards.
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
+
+    /// Sets the playback speed (see Request::speed). Negative speeds play backwards. When
+    /// called before the first `process` call, playback starts at the end of the input for
+    /// negative speeds.
+    ///
+    /// # Errors
+    /// Returns an error if the speed is not finite.
+    pub fn set_speed(&mut self, speed: f64) -> Result<(), Error> {
+        if !speed.is_finite() {
+            return Err(Error::InvalidSpeed(speed));
+        }
+        self.speed = speed;
+        Ok(())
+    }
+
+    /// Returns the current pitch shift.
+    pub fn pitch(&self) -> f64 {
+        self.pitch
+    }
+
+    /// Sets the pitch shift (see Request::pitch).
+    ///
+    /// # Errors
+    /// Returns an error if the pitch is not positive and finite.
+    pub fn set_pitch(&mut self, pitch: f64) -> Result<(), Error> {
+        if !(pitch.is_finite() && pitch > 0.0) {
+            return Err(Error::InvalidPitch(pitch));
+        }
+        self.pitch = pitch;
+        Ok(())
+    }
+
+    /// Returns the current loop mode.
+    pub fn loop_mode(&self) -> LoopMode {
+        self.loop_mode
+    }
+
+    /// Sets the loop mode, which applies to the loop range.
+    pub fn set_loop_mode(&mut self, loop_mode: LoopMode) {
+        self.loop_mode = loop_mode;
+        self.playback.set_bounds(self.output_bounds());
+    }
+
+    /// Returns the loop range in input frames. Defaults to the entire input.
+    pub fn loop_range(&self) -> Range<usize> {
+        self.loop_range.clone()
+    }
+
+    /// Sets the loop range in input frames.
+    ///
+    /// # Errors
+    /// Returns an error if the range is empty or exceeds the input.
+    pub fn set_loop_range(&mut self, range: Range<usize>) -> Result<(), Error> {
+        if range.is_empty() || range.end > self.input_len() {
+            return Err(Error::InvalidRange {
+                start: range.start,
+                end: range.end,
+            });
+        }
+        self.loop_range = range;
+        Ok(())
+    }
+
+    /// Input frame position of the most recently rendered output frame.
+    pub fn position(&self) -> f64 {
+        self.playback.position()
+    }
+
+    /// Jumps to the given input frame position. Pending output is discarded, and the stretcher
+    /// restarts on the new position with the next `process` call.
+    ///
+    /// # Errors
+    /// Returns an error if the position is not finite.
+    pub fn seek(&mut self, position: f64) -> Result<(), Error> {
+        if !position.is_finite() {
+            return Err(Error::NonFinitePosition(position));
+        }
+        self.restart = Some(position);
+        self.playback.discard_output(position);
+        if let Some(scrub) = &mut self.scrub {
+            scrub.from = position;
+            scrub.cursor = position;
+        }
+        Ok(())
+    }
+
+    /// Returns true when the player is in scrub mode.
+    pub fn is_scrubbing(&self) -> bool {
+        self.scrub.is_some()
+    }
+
+    /// Enters scrub mode, or moves the scrub cursor when already scrubbing.
+    ///
+    /// In scrub mode, the playback position follows the cursor: with each `process` call, grain
+    /// positions move from the previous to the current cursor position, and the grain speed is
+    /// derived from the distance between successive grain positions (see Request::speed). A
+    /// cursor which doesn't move holds the audio at its position. The cursor is clamped to the
+    /// input audio, and loop settings are ignored while scrubbing.
+    ///
+    /// # Errors
+    /// Returns an error if the position is not finite.
+    pub fn scrub(&mut self, position: f64) -> Result<(), Error> {
+        if !position.is_finite() {
+            return Err(Error::NonFinitePosition(position));
+        }
+        let position = position.clamp(0.0, self.input_len() as f64);
+        match &mut self.scrub {
+            Some(scrub) => scrub.cursor = position,
+            None => {
+                if !self.started && self.restart.is_none() {
+                    self.restart = Some(position);
+                }
+                let from = if self.restart.is_some() {
+                    position
+                } else {
+                    self.request.position
+                };
+                self.scrub = Some(Scrub {
+                    from,
+                    cursor: position,
+                    block_frames: 0,
+                    rendered_frames: 0,
+                });
+                self.playback.set_bounds(self.output_bounds());
+            }
+        }
+        Ok(())
+    }
+
+    /// Leaves scrub mode. Playback continues from the current position with the player's speed.
+    pub fn stop_scrubbing(&mut self) {
+        if self.scrub.take().is_some() {
+            self.playback.set_bounds(self.output_bounds());
+        }
+    }
+
+    /// Returns true when playback reached the end of the input without looping and all
+    /// remaining output frames got rendered.
+    pub fn is_finished(&self) -> bool {
+        self.playback.is_finished()
+    }
+
+    /// Renders the next block of output audio. The block size is the length of the first
+    /// output channel. Returns the number of rendered frames, which is less than the block
+    /// size only when the playback finished: remaining frames are filled with silence.
+    ///
+    /// # Panics
+    /// Panics if the output buffers are invalid. See [`Self::try_process`].
+    pub fn process<O: AsMut<[f32]>>(&mut self, output: &mut [O]) -> usize {
+        self.try_process(output)
+            .unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Renders the next block of output audio, just like [`Self::process`], but returns an
+    /// error instead of panicking when the output buffers are invalid.
+    ///
+    /// # Errors
+    /// Returns an error if the number of output channels doesn't match the player's channel
+    /// count or if an output channel is shorter than the first one.
+    pub fn try_process<O: AsMut<[f32]>>(&mut self, output: &mut [O]) -> Result<usize, Error> {
+        let frame_count = self.playback.block_frame_count(output)?;
+        if let Some(scrub) = &mut self.scrub {
+            scrub.block_frames = frame_count;
+            scrub.rendered_frames = 0;
+        }
+        let mut rendered = self.playback.pop(output, 0..frame_count);
+        while rendered < frame_count && self.playback.needs_grain() {
+            self.render_grain();
+            rendered += self.playback.pop(output, rendered..frame_count);
+        }
+        if let Some(scrub) = &mut self.scrub {
+            scrub.from = scrub.cursor;
+        }
+        fill_silence(output, rendered..frame_count);
+        Ok(rendered)
+    }
+
+    /// (Re)starts playback at the given position: the stretcher forgets all previous grains
+    /// and runs in until the output reaches the position.
+    fn start(&mut self, position: f64) {
+        let speed = if self.scrub.is_some() {
+            0.0
+        } else {
+            self.speed
+        };
+        self.request = Request {
+            position,
+            speed,
+            pitch: self.pitch,
+            reset: true,
+        };
+        let direction = if speed < 0.0 { -1.0 } else { 1.0 };
+        let bounds = self.output_bounds();
+        self.playback.start(&mut self.request, direction, bounds);
+        self.started = true;
+    }
+
+    /// Runs the stretcher's grain loop for a single grain.
+    fn render_grain(&mut self) {
+        if !self.started && self.restart.is_none() {
+            // start at the start or end of the input, depending on the playback direction
+            self.restart = Some(if self.speed < 0.0 {
+                self.input_len().saturating_sub(1) as f64
+            } else {
+                0.0
+            });
+        }
+        if let Some(position) = self.restart.take() {
+            self.start(position);
+        }
+
+        let position = self.request.position;
+        let hop = self.playback.render_grain(&mut self.request);
+        self.request.pitch = self.pitch;
+        if let Some(scrub) = &mut self.scrub {
+            // move towards the cursor within the current output block
+            scrub.rendered_frames += hop;
+            let fraction = scrub.rendered_frames as f64 / scrub.block_frames.max(1) as f64;
+            let next_position = scrub.from + (scrub.cursor - scrub.from) * fraction.min(1.0);
+            let distance = next_position - position;
+            self.request.position = next_position;
+            self.request.speed = if hop > 0 { distance / hop as f64 } else { 0.0 };
+            // jumps which exceed a grain are discontinuities
+            self.request.reset = distance.abs() > self.playback.max_input_frame_count() as f64;
+        } else {
+            self.request.speed = self.speed;
+            self.apply_loop();
+        }
+    }
+
+    /// Wraps or reflects the next grain's position at the loop range boundaries.
+    fn apply_loop(&mut self) {
+        let start = self.loop_range.start as f64;
+        let end = self.loop_range.end as f64;
+        let position = self.request.position;
+        let forward = self.speed >= 0.0;
+        match self.loop_mode {
+            LoopMode::Off => (),
+            LoopMode::Loop => {
+                // jump to the opposite end of the loop without resetting the stretcher, which
+                // continues the phases of the previous grains and overlaps them with the new ones
+                let length = end - start;
+                if forward && position >= end {
+                    self.request.position = start + (position - end) % length;
+                } else if !forward && position < start {
+                    self.request.position = end - (start - position) % length;
+                }
+            }
+            LoopMode::PingPong => {
+                // continuous: reflect the position and reverse the direction
+                if (forward && position >= end) || (!forward && position < start) {
+                    let bound = if forward { end } else { start };
+                    self.request.position = (2.0 * bound - position).clamp(start, end);
+                    self.speed = -self.speed;
+                    self.request.speed = self.speed;
+                }
+            }
+        }
+    }
+
+    /// Valid input positions of the output when not looping or scrubbing.
+    fn output_bounds(&self) -> Option<Range<f64>> {
+        if self.loop_mode == LoopMode::Off && self.scrub.is_none() {
+            Some(0.0..self.input_len() as f64)
+        } else {
+            None
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+/// Scrub mode state: grain positions move from `from` to `cursor` within an output block.
+struct Scrub {
+    from: f64,
+    cursor: f64,
+    block_frames: usize,
+    rendered_frames: usize,
+}
+
+// -------------------------------------------------------------------------------------------------
+
+/// Request of a player which has not been started yet: it gets replaced when starting.
+pub(crate) const IDLE_REQUEST: Request = Request {
+    position: 0.0,
+    speed: 1.0,
+    pitch: 1.0,
+    reset: true,
+};
+
+/// Fills the given frame range of the output buffers with silence.
+pub(crate) fn fill_silence<O: AsMut<[f32]>>(output: &mut [O], frames: Range<usize>) {
+    for samples in output.iter_mut() {
+        samples.as_mut()[frames.clone()].fill(0.0);
+    }
+}
+
+/// Stretcher, grain input and output FIFO of a player: renders grains of an audio source for
+/// the player's requests, and output blocks of any size from them.
+pub(crate) struct Playback<S> {
+    stretcher: Stretcher,
+    source: S,
+    input_len: usize,
+    grain_input: GrainInput,
+    output: OutputFifo,
+}
+
+impl<S: AudioSource> Playback<S> {
+    pub fn new(source: S, sample_rate: usize) -> Result<Self, Error> {
+        let num_channels = source.channels();
+        let input_len = source.len();
+
+        let stretcher = Stretcher::new(sample_rate, num_channels)?;
+        let grain_input = GrainInput::new(&stretcher);
+        // the FIFO holds at most one grain, see `needs_grain`, and a grain's output never
+        // exceeds the stretcher's largest input chunk
+        let output = OutputFifo::new(num_channels, stretcher.max_input_frame_count());
+
+        Ok(Self {
+            stretcher,
+            source,
+            input_len,
+            grain_input,
+            output,
+        })
+    }
+
+    pub fn sample_rate(&self) -> usize {
+        self.stretcher.sample_rate()
+    }
+
+    pub fn num_channels(&self) -> usize {
+        self.stretcher.num_channels()
+    }
+
+    pub fn source(&self) -> &S {
+        &self.source
+    }
+
+    pub fn input_len(&self) -> usize {
+        self.input_len
+    }
+
+    pub fn max_input_frame_count(&self) -> usize {
+        self.stretcher.max_input_frame_count()
+    }
+
+    /// Input position of the most recently popped output frame.
+    pub fn position(&self) -> f64 {
+        self.output.position
+    }
+
+    /// Returns true when the output left its bounds and all remaining frames got popped.
+    pub fn is_finished(&self) -> bool {
+        self.output.end_reached && self.output.is_empty()
+    }
+
+    /// Sets the valid input positions of the output, or None for unbounded output.
+    pub fn set_bounds(&mut self, bounds: Option<Range<f64>>) {
+        self.output.bounds = bounds;
+    }
+
+    /// Discards pending output and moves the position, e.g. when seeking.
+    pub fn discard_output(&mut self, position: f64) {
+        self.output.discard(position);
+    }
+
+    /// Restarts the stretcher on the request's position: the request gets prerolled, and
+    /// output is skipped until it reaches the position in the given direction.
+    pub fn start(&mut self, request: &mut Request, direction: f64, bounds: Option<Range<f64>>) {
+        let position = request.position;
+        self.stretcher.preroll(request);
+        self.output.discard(position);
+        self.output.skip_until(position, direction);
+        self.output.bounds = bounds;
+    }
+
+    /// Runs the stretcher's grain loop for a single grain and prepares the request's position
+    /// for the next grain. Returns the grain's output frame count.
+    pub fn render_grain(&mut self, request: &mut Request) -> usize {
+        let input_chunk = self.stretcher.specify_grain(request);
+        self.grain_input
+            .analyse(&mut self.stretcher, &mut self.source, input_chunk);
+        let output_chunk = self.stretcher.synthesise_grain();
+        let hop = output_chunk.frame_count;
+        self.output.push(&output_chunk);
+        self.stretcher.next(request);
+        hop
+    }
+
+    /// Verifies the output buffers of a process call and returns its block size: the length
+    /// of the first output channel.
+    pub fn block_frame_count<O: AsMut<[f32]>>(&self, output: &mut [O]) -> Result<usize, Error> {
+        if output.len() != self.num_channels() {
+            return Err(Error::ChannelMismatch {
+                expected: self.num_channels(),
+                actual: output.len(),
+            });
+        }
+        let frame_count = output.first_mut().map_or(0, |c| c.as_mut().len());
+        for (channel, samples) in output.iter_mut().enumerate() {
+            let len = samples.as_mut().len();
+            if len < frame_count {
+                return Err(Error::BufferTooSmall {
+                    channel,
+                    len,
+                    required: frame_count,
+                });
+            }
+        }
+        Ok(frame_count)
+    }
+
+    /// Returns true when all rendered output was popped and the next grain needs to be
+    /// rendered. Grains only get rendered into an empty FIFO, so its preallocated capacity is
+    /// never exceeded, whatever the output block size.
+    pub fn needs_grain(&self) -> bool {
+        self.output.is_empty() && !self.output.end_reached
+    }
+
+    /// Pops up to `frames.len()` output frames into the given frame range of the output
+    /// buffers. Returns the number of popped frames.
+    pub fn pop<O: AsMut<[f32]>>(&mut self, output: &mut [O], frames: Range<usize>) -> usize {
+        self.output.pop(output, frames)
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+/// Planar FIFO of rendered output frames and their input positions.
+struct OutputFifo {
+    channels: Vec<Vec<f32>>,
+    positions: Vec<f64>,
+    read_pos: usize,
+    /// Input position of the most recently popped frame.
+    position: f64,
+    /// Skip frames until reaching the given input position in the given direction.
+    skip_until: Option<(f64, f64)>,
+    /// Remaining number of frames to skip, in case the position never gets reached.
+    skip_budget: usize,
+    max_skip_frames: usize,
+    /// Stop accepting frames when leaving the given input position range.
+    bounds: Option<Range<f64>>,
+    end_reached: bool,
+}
+
+impl OutputFifo {
+    fn new(num_channels: usize, capacity: usize) -> Self {
+        Self {
+            channels: vec![Vec::with_capacity(capacity); num_channels],
+            positions: Vec::with_capacity(capacity),
+            read_pos: 0,
+            position: 0.0,
+            skip_until: None,
+            skip_budget: 0,
+            max_skip_frames: 4 * capacity,
+            bounds: None,
+            end_reached: false,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.positions.len() - self.read_pos
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Skips all following frames until reaching the given input position in the given
+    /// direction.
+    fn skip_until(&mut self, position: f64, direction: f64) {
+        self.skip_until = Some((position, direction));
+        self.skip_budget = self.max_skip_frames;
+    }
+
+    /// Discards all frames and moves the position.
+    fn discard(&mut self, position: f64) {
+        for samples in &mut self.channels {
+            samples.clear();
+        }
+        self.positions.clear();
+        self.read_pos = 0;
+        self.position = position;
+        self.end_reached = false;
+    }
+
+    fn push(&mut self, chunk: &OutputChunk) {
+        self.compact();
+
+        // frames of a chunk which ends on a reset grain have no input positions: their start
+        // belongs to the grain before the reset, e.g. before a seek
+        let frame_count = chunk.frame_count;
+        let (begin, step) = match chunk.request {
+            [Some(begin), Some(end)] if frame_count > 0 && !end.reset => (
+                begin.position,
+                (end.position - begin.position) / frame_count as f64,
+            ),
+            _ => (f64::NAN, 0.0),
+        };
+        let position = |frame: usize| begin + step * frame as f64;
+
+        let mut first_frame = 0;
+        if let Some((start, direction)) = self.skip_until {
+            first_frame = (0..frame_count)
+                .find(|&frame| (position(frame) - start) * direction >= 0.0)
+                .unwrap_or(frame_count)
+                .min(self.skip_budget);
+            self.skip_budget -= first_frame;
+            if first_frame < frame_count {
+                self.skip_until = None;
+            }
+        }
+        let mut last_frame = frame_count;
+        if let Some(bounds) = &self.bounds {
+            if let Some(frame) = (first_frame..frame_count).find(|&frame| {
+                let position = position(frame);
+                !position.is_nan() && !bounds.contains(&position)
+            }) {
+                last_frame = frame;
+                self.end_reached = true;
+            }
+        }
+
+        for (channel, samples) in self.channels.iter_mut().enumerate() {
+            samples.extend_from_slice(&chunk.channel(channel)[first_frame..last_frame]);
+        }
+        self.positions
+            .extend((first_frame..last_frame).map(position));
+    }
+
+    fn pop<O: AsMut<[f32]>>(&mut self, output: &mut [O], frames: Range<usize>) -> usize {
+        let frame_count = frames.len().min(self.len());
+        for (samples, output) in self.channels.iter().zip(output.iter_mut()) {
+            output.as_mut()[frames.start..frames.start + frame_count]
+                .copy_from_slice(&samples[self.read_pos..self.read_pos + frame_count]);
+        }
+        self.read_pos += frame_count;
+        if frame_count > 0 {
+            let position = self.positions[self.read_pos - 1];
+            if !position.is_nan() {
+                self.position = position;
+            }
+        }
+        frame_count
+    }
+
+    /// Moves pending frames to the front of the buffers.
+    fn compact(&mut self) {
+        if self.read_pos > 0 {
+            for samples in &mut self.channels {
+                samples.copy_within(self.read_pos.., 0);
+                samples.truncate(samples.len() - self.read_pos);
+            }
+            self.positions.copy_within(self.read_pos.., 0);
+            self.positions
+                .truncate(self.positions.len() - self.read_pos);
+            self.read_pos = 0;
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_signals::{average_magnitude, sine, SAMPLE_RATE};
+
+    /// 440 Hz and 1250 Hz sines.
+    fn test_signal(len: usize) -> Vec<f32> {
+        let high = sine(1250.0, 0.25, len);
+        sine(440.0, 0.5, len)
+            .iter()
+            .zip(high)
+            .map(|(low, high)| low + high)
+            .collect()
+    }
+
+    fn render_all(player: &mut Player) -> Vec<f32> {
+        let mut rendered = Vec::new();
+        let mut output = vec![vec![0.0f32; 512]];
+        for _ in 0..1000 {
+            let frame_count = player.process(&mut output);
+            rendered.extend_from_slice(&output[0][..frame_count]);
+            if player.is_finished() {
+                break;
+            }
+        }
+        rendered
+    }
+
+    #[test]
+    fn player_reverse() {
+        let input = test_signal(SAMPLE_RATE);
+
+        let mut player = Player::new(vec![input.clone()], SAMPLE_RATE).unwrap();
+        let forward = render_all(&mut player);
+        assert!(player.is_finished());
+
+        let mut player = Player::new(vec![input.clone()], SAMPLE_RATE).unwrap();
+        player.set_speed(-1.0).unwrap();
+        let mut reverse = render_all(&mut player);
+        assert!(player.is_finished());
+        reverse.reverse();
+
+        assert!(forward.len().abs_diff(input.len()) < 64);
+        assert!(reverse.len().abs_diff(input.len()) < 64);
+
+        // compare spectral content, ignoring edges
+        let forward = &forward[4096..forward.len() - 4096];
+        let reverse = &reverse[4096..reverse.len() - 4096];
+        for frequency in [440.0, 1250.0] {
+            let forward_magnitude = average_magnitude(forward, frequency, 4096);
+            let reverse_magnitude = average_magnitude(reverse, frequency, 4096);
+            assert!(forward_magnitude > 0.02);
+            assert!((forward_magnitude - reverse_magnitude).abs() < 0.2 * forward_magnitude);
+        }
+        assert!(
+            average_magnitude(reverse, 3000.0, 4096)
+                < 0.1 * average_magnitude(reverse, 440.0, 4096)
+        );
+    }
+
+    #[test]
+    fn player_ping_pong() {
+        let input = test_signal(SAMPLE_RATE);
+        let mut player = Player::new(vec![input], SAMPLE_RATE).unwrap();
+        player.set_loop_mode(LoopMode::PingPong);
+        player.set_loop_range(10000..30000).unwrap();
+        player.set_speed(1.5).unwrap();
+
+        let mut output = vec![vec![0.0f32; 512]];
+        let mut positions = Vec::new();
+        for _ in 0..(3 * SAMPLE_RATE / 512) {
+            assert_eq!(player.process(&mut output), 512);
+            assert!(output[0].iter().all(|s| s.is_finite()));
+            positions.push(player.position());
+        }
+        assert!(!player.is_finished());
+
+        // after entering the loop range, position stays within it (+- a grain) and changes
+        // direction
+        let positions = &positions[SAMPLE_RATE / 512..];
+        assert!(positions.iter().all(|p| (8000.0..32000.0).contains(p)));
+        let directions = positions
+            .windows(2)
+            .map(|w| w[1] > w[0])
+            .collect::<Vec<_>>();
+        assert!(directions.windows(2).filter(|d| d[0] != d[1]).count() >= 2);
+
+        assert!(matches!(
+            player.set_loop_range(100..100),
+            Err(Error::InvalidRange { .. })
+        ));
+    }
+
+    #[test]
+    fn player_loop() {
+        // a 441 Hz sine with a loop range of exactly 200 periods loops seamlessly
+        let input = sine(441.0, 0.5, SAMPLE_RATE);
+        let mut player = Player::new(vec![input], SAMPLE_RATE).unwrap();
+        player.set_loop_mode(LoopMode::Loop);
+        player.set_loop_range(10000..30000).unwrap();
+
+        let mut output = vec![vec![0.0f32; 512]];
+        let mut rendered = Vec::new();
+        let mut positions = Vec::new();
+        for _ in 0..(2 * SAMPLE_RATE / 512) {
+            assert_eq!(player.process(&mut output), 512);
+            rendered.extend_from_slice(&output[0]);
+            positions.push(player.position());
+        }
+        assert!(!player.is_finished());
+
+        // position wraps around at least twice
+        let wraps = positions
+            .windows(2)
+            .filter(|w| w[1] < w[0] - 10000.0)
+            .count();
+        assert!(wraps >= 2);
+
+        // loop points don't click: sample to sample differences stay close to the sine's
+        // maximum slope of 0.5 * 2pi * 441 / 44100
+        let max_slope = 0.5 * std::f32::consts::TAU * 441.0 / SAMPLE_RATE as f32;
+        let max_difference = rendered[4096..]
+            .windows(2)
+            .map(|w| (w[1] - w[0]).abs())
+            .fold(0.0f32, f32::max);
+        assert!(
+            max_difference < 1.5 * max_slope,
+            "discontinuity of {max_difference} exceeds the sine's slope of {max_slope}"
+        );
+    }
+
+    #[test]
+    fn player_seek() {
+        let input = test_signal(SAMPLE_RATE * 2);
+        let mut player = Player::new(vec![input.as_slice()], SAMPLE_RATE).unwrap();
+
+        let mut output = vec![vec![0.0f32; 512]];
+        for _ in 0..20 {
+            player.process(&mut output);
+        }
+        assert!((player.position() - 20.0 * 512.0).abs() < 64.0);
+
+        // seek restarts exactly on the new position
+        player.seek(50000.0).unwrap();
+        assert_eq!(player.position(), 50000.0);
+        assert_eq!(player.process(&mut output), 512);
+        assert!((player.position() - 50512.0).abs() < 2.0);
+        for _ in 0..20 {
+            player.process(&mut output);
+        }
+        assert!((player.position() - 50512.0 - 20.0 * 512.0).abs() < 64.0);
+
+        // also when seeking backwards, which must not output frames of the previous grains
+        player.seek(10000.0).unwrap();
+        assert_eq!(player.process(&mut output), 512);
+        assert!((player.position() - 10512.0).abs() < 2.0);
+
+        // seeking into the end finishes playback
+        player.seek(SAMPLE_RATE as f64 * 2.0 - 100.0).unwrap();
+        assert!(player.process(&mut output) <= 100);
+        assert!(player.is_finished());
+
+        assert!(matches!(
+            player.seek(f64::INFINITY),
+            Err(Error::NonFinitePosition(_))
+        ));
+    }
+
+    #[test]
+    fn player_process_does_not_allocate() {
+        let input = test_signal(SAMPLE_RATE * 2);
+        let mut player = Player::new(vec![input], SAMPLE_RATE).unwrap();
+        // blocks which span several grains
+        let block_size = 4 * player.playback.max_input_frame_count();
+        let mut output = vec![vec![0.0f32; block_size]];
+        let mut small_output = vec![vec![0.0f32; 100]];
+
+        let (_, allocations) = crate::test_alloc::count_allocations(|| {
+            for speed in [1.0, 0.5, 2.0, -1.0] {
+                player.set_speed(speed).unwrap();
+                player.process(&mut output);
+                player.process(&mut small_output);
+            }
+            player.seek(10000.0).unwrap();
+            player.process(&mut output);
+            player.set_loop_mode(LoopMode::PingPong);
+            player.process(&mut output);
+            player.scrub(20000.0).unwrap();
+            player.process(&mut output);
+        });
+        assert_eq!(allocations, 0);
+    }
+
+    #[test]
+    fn player_scrub() {
+        let input = test_signal(SAMPLE_RATE * 2);
+        let mut player = Player::new(vec![input], SAMPLE_RATE).unwrap();
+
+        // cursor moves forward at half speed
+        let mut output = vec![vec![0.0f32; 512]];
+        let mut cursor = 20000.0;
+        for _ in 0..50 {
+            player.scrub(cursor).unwrap();
+            assert_eq!(player.process(&mut output), 512);
+            assert!(output[0].iter().all(|s| s.is_finite()));
+            cursor += 256.0;
+        }
+        assert!(player.is_scrubbing());
+        assert!((player.position() - cursor).abs() < 4096.0);
+
+        // then backwards at normal speed
+        let turn_position = player.position();
+        for _ in 0..50 {
+            player.scrub(cursor).unwrap();
+            player.process(&mut output);
+            cursor -= 512.0;
+        }
+        assert!(player.position() < turn_position - 10000.0);
+
+        // and holds when the cursor stops
+        for _ in 0..20 {
+            player.scrub(cursor).unwrap();
+            player.process(&mut output);
+        }
+        assert!((player.position() - cursor).abs() < 1024.0);
+
+        // leaving scrub mode continues playback forward
+        player.stop_scrubbing();
+        let position = player.position();
+        for _ in 0..20 {
+            player.process(&mut output);
+        }
+        assert!(player.position() > position + 5000.0);
+    }
+}
@@ -0,0 +1,151 @@
+//! Real-time safety of the audio processing paths.
+//!
+//! Types which implement [`RealtimeSafe`] have real-time entry points, whose Rust code neither
+//! allocates, locks nor blocks once the type has been constructed and warmed up. Each
+//! implementation lists its entry points.
+//!
+//! Only the Rust side is checked: the Bungee C++ library allocates via `operator new` and
+//! `malloc`, which bypass Rust's global allocator, so neither the checks below nor this crate's
+//! tests detect allocations within Bungee itself.
+//!
+//! With the `debug_assert_no_alloc` feature, the bodies of the entry points of [`Stretcher`],
+//! [`Stream`] and [`GrainInputBuffer`] are wrapped into
+//! [`assert_no_alloc`](https://docs.rs/assert_no_alloc), so debug builds which install its
+//! [`AllocDisabler`] as global allocator abort when an entry point allocates on the Rust side:
+//!
+//! ```rust, ignore
+//! #[cfg(debug_assertions)]
+//! #[global_allocator]
+//! static ALLOCATOR: bungee_rs::realtime::AllocDisabler = bungee_rs::realtime::AllocDisabler;
+//! ```
+
+use crate::{
+    control::StreamController, edition::EditionMarker, input_buffer::GrainInputBuffer, Stream,
+    Stretcher,
+};
+
+#[cfg(feature = "debug_assert_no_alloc")]
+pub use assert_no_alloc::AllocDisabler;
+
+// -------------------------------------------------------------------------------------------------
+
+/// Marker for types with real-time safe entry points. See the [module docs](self).
+pub trait RealtimeSafe {}
+
+/// Real-time entry points: `preroll`, `specify_grain`, `specify_grain_in_buffer`,
+/// `analyse_grain`, `analyse_grain_with_mute`, `synthesise_grain`, `next`, `is_flushed` and
+/// their `try_` variants, as long as they don't fail.
+impl<E: EditionMarker> RealtimeSafe for Stretcher<E> {}
+
+/// Real-time entry points: `process`, `process_planar`, `process_interleaved`,
+/// `process_controlled`, `finish`, the position and latency getters and the `try_` variants of
+/// the process functions, as long as they don't fail.
+impl<E: EditionMarker> RealtimeSafe for Stream<E> {}
+
+/// Real-time entry points: all getters and setters, and `jump_to_targets`.
+impl RealtimeSafe for StreamController {}
+
+/// Real-time entry points: `push` and `analyse`.
+impl RealtimeSafe for GrainInputBuffer {}
+
+// -------------------------------------------------------------------------------------------------
+
+/// Runs the body of a real-time entry point. With the `debug_assert_no_alloc` feature, Rust
+/// allocations within `f` trigger `assert_no_alloc`'s allocator checks. Allocations of the
+/// Bungee C++ library are not seen by Rust's global allocator, so they pass unnoticed.
+#[inline]
+pub(crate) fn guard<R>(f: impl FnOnce() -> R) -> R {
+    #[cfg(feature = "debug_assert_no_alloc")]
+    {
+        assert_no_alloc::assert_no_alloc(f)
+    }
+    #[cfg(not(feature = "debug_assert_no_alloc"))]
+    {
+        f()
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_alloc::count_allocations, Request, StreamBuilder};
+
+    // Counts Rust allocations only: allocations within the Bungee C++ library bypass the global
+    // allocator, so this verifies the Rust side of the entry points.
+    #[test]
+    fn realtime_entry_points() {
+        const SAMPLE_RATE: usize = 44100;
+        const FRAME_COUNT: usize = 512;
+
+        // stretcher grain loop
+        let mut stretcher = Stretcher::new(SAMPLE_RATE, 2).unwrap();
+        let mut buffer = GrainInputBuffer::new(&stretcher, SAMPLE_RATE);
+        let mut request = Request {
+            pitch: 1.5,
+            speed: 0.75,
+            position: 0.0,
+            reset: true,
+        };
+        let input = vec![0.25f32; FRAME_COUNT];
+        let mut render = |stretcher: &mut Stretcher, request: &mut Request| {
+            buffer.push(&[&input, &input]).unwrap();
+            while buffer.covers(stretcher, request) {
+                buffer.analyse(stretcher, request).unwrap();
+                let _ = stretcher.synthesise_grain().frame_count;
+                stretcher.next(request);
+            }
+            stretcher.is_flushed()
+        };
+        // warm-up
+        stretcher.preroll(&mut request);
+        for _ in 0..16 {
+            render(&mut stretcher, &mut request);
+        }
+        let (_, allocations) = count_allocations(|| {
+            for _ in 0..64 {
+                render(&mut stretcher, &mut request);
+            }
+        });
+        assert_eq!(allocations, 0);
+
+        // stream processing
+        let mut stream = StreamBuilder::new(SAMPLE_RATE, 2, FRAME_COUNT)
+            .max_output_frame_count(FRAME_COUNT * 2)
+            .build()
+            .unwrap();
+        let planar_input = vec![vec![0.25f32; FRAME_COUNT]; 2];
+        let mut planar_output = vec![vec![0.0f32; FRAME_COUNT * 2]; 2];
+        let interleaved_input = vec![0.25f32; FRAME_COUNT * 2];
+        let mut interleaved_output = vec![0.0f32; FRAME_COUNT * 4];
+        let mut process = |stream: &mut Stream| {
+            stream.process(
+                Some(&planar_input),
+                &mut planar_output,
+                FRAME_COUNT,
+                700.0,
+                1.0,
+            );
+            stream.process_controlled(Some(&planar_input), &mut planar_output, FRAME_COUNT);
+            stream.process_interleaved(
+                Some(&interleaved_input),
+                &mut interleaved_output,
+                FRAME_COUNT,
+                FRAME_COUNT as f64 * 1.5,
+                0.5,
+            );
+            stream.output_position() + stream.latency()
+        };
+        // warm-up
+        for _ in 0..16 {
+            process(&mut stream);
+        }
+        let (_, allocations) = count_allocations(|| {
+            for _ in 0..64 {
+                process(&mut stream);
+            }
+        });
+        assert_eq!(allocations, 0);
+    }
+}
@@ -0,0 +1,255 @@
+//! Time-stretched playback of [rodio](https://docs.rs/rodio) sources.
+
+use std::time::Duration;
+
+use ::rodio::{ChannelCount, SampleRate, Source};
+
+use crate::{Error, Stream, StreamBuilder, StreamController};
+
+// -------------------------------------------------------------------------------------------------
+
+/// Number of input frames which get stretched at once.
+const BLOCK_SIZE: usize = 512;
+
+// -------------------------------------------------------------------------------------------------
+
+/// A rodio [`Source`] which time-stretches and pitch-shifts another source with a [`Stream`].
+///
+/// Speed and pitch can be changed while the source is playing via [`Self::set_speed`] and
+/// [`Self::set_pitch`], or via its [`StreamController`] after the source got moved into a
+/// rodio player. The stream compensates Bungee's latency and gets drained at the end of the
+/// wrapped source, so the output starts in sync and is exactly `len / speed` frames long.
+///
+/// The wrapped source's channel count and sample rate must not change while playing. Wrap it
+/// into a [`rodio::source::UniformSourceIterator`](::rodio::source::UniformSourceIterator)
+/// otherwise.
+///
+/// ```rust, no_run
+/// use bungee_rs::rodio::StretchedSource;
+/// use rodio::buffer::SamplesBuffer;
+///
+/// fn main() -> Result<(), bungee_rs::Error> {
+///     let source = SamplesBuffer::new(2, 44100, vec![0.0f32; 2 * 44100]);
+///     let stretched = StretchedSource::new(source)?;
+///     let controller = stretched.controller();
+///     controller.set_speed(0.5)?;
+///
+///     // e.g. append `stretched` to a `rodio::Sink`, or pull samples from it directly:
+///     let samples = stretched.collect::<Vec<f32>>();
+///     assert_eq!(samples.len(), 2 * 2 * 44100);
+///     Ok(())
+/// }
+/// ```
+pub struct StretchedSource<S: Source<Item = f32>> {
+    source: S,
+    stream: Stream,
+    controller: StreamController,
+    input: Vec<Vec<f32>>,
+    output: Vec<Vec<f32>>,
+    output_len: usize,
+    output_pos: usize,
+    channel: usize,
+    source_finished: bool,
+    stream_finished: bool,
+}
+
+impl<S: Source<Item = f32>> StretchedSource<S> {
+    /// Creates a new stretched source, which initially plays the given source at its original
+    /// speed and pitch.
+    ///
+    /// # Errors
+    /// Returns an error if the stream for the source's channel count and sample rate cannot be
+    /// created.
+    pub fn new(source: S) -> Result<Self, Error> {
+        let num_channels = source.channels() as usize;
+        let sample_rate = source.sample_rate() as usize;
+        let stream = StreamBuilder::new(sample_rate, num_channels, BLOCK_SIZE)
+            .latency_compensation(true)
+            .build()?;
+        let controller = stream.controller();
+        Ok(Self {
+            source,
+            stream,
+            controller,
+            input: vec![vec![0.0; BLOCK_SIZE]; num_channels],
+            output: vec![vec![0.0; BLOCK_SIZE]; num_channels],
+            output_len: 0,
+            output_pos: 0,
+            channel: 0,
+            source_finished: false,
+            stream_finished: false,
+        })
+    }
+
+    /// Returns the wrapped source.
+    pub fn inner(&self) -> &S {
+        &self.source
+    }
+
+    /// Returns a new handle which controls speed, pitch and smoothing of the source from other
+    /// threads, e.g. after the source got moved into a rodio player.
+    pub fn controller(&self) -> StreamController {
+        self.controller.clone()
+    }
+
+    /// Sets the playback speed. See [`StreamController::set_speed`].
+    ///
+    /// # Errors
+    /// Returns an error if the speed is not a positive, finite number.
+    pub fn set_speed(&self, speed: f64) -> Result<(), Error> {
+        self.controller.set_speed(speed)
+    }
+
+    /// Sets the pitch as a frequency multiplier. See [`StreamController::set_pitch`].
+    ///
+    /// # Errors
+    /// Returns an error if the pitch is not a positive, finite number.
+    pub fn set_pitch(&self, pitch: f64) -> Result<(), Error> {
+        self.controller.set_pitch(pitch)
+    }
+
+    /// Stretches the next block of input into the output buffers. Returns false when the
+    /// source and stream are exhausted.
+    fn refill(&mut self) -> bool {
+        self.output_len = 0;
+        self.output_pos = 0;
+        while self.output_len == 0 {
+            if self.stream_finished {
+                return false;
+            }
+            if self.source_finished {
+                // drain the stream's tail
+                let frame_count = self.stream.finish_frame_count();
+                Self::reserve(&mut self.output, frame_count);
+                self.output_len = self.stream.finish(&mut self.output);
+                self.stream_finished = true;
+                continue;
+            }
+
+            // deinterleave the next block of input, padding incomplete frames with silence
+            let num_channels = self.input.len();
+            let mut frame_count = 0;
+            'frames: while frame_count < BLOCK_SIZE {
+                for channel in 0..num_channels {
+                    match self.source.next() {
+                        Some(sample) => self.input[channel][frame_count] = sample,
+                        None => {
+                            self.source_finished = true;
+                            if channel == 0 {
+                                break 'frames;
+                            }
+                            self.input[channel..]
+                                .iter_mut()
+                                .for_each(|input| input[frame_count] = 0.0);
+                            frame_count += 1;
+                            break 'frames;
+                        }
+                    }
+                }
+                frame_count += 1;
+            }
+            if frame_count == 0 {
+                continue;
+            }
+
+            let block = self.stream.next_controlled_block(frame_count);
+            Self::reserve(&mut self.output, block.output_frame_count.ceil() as usize);
+            self.output_len = self.stream.process_planar(
+                Some(&self.input),
+                &mut self.output,
+                frame_count,
+                block.output_frame_count,
+                block.pitch,
+            );
+            self.stream.commit_controlled_block(block);
+        }
+        true
+    }
+
+    /// Grows the planar output buffers to hold at least `frame_count` frames.
+    fn reserve(output: &mut [Vec<f32>], frame_count: usize) {
+        for channel in output.iter_mut().filter(|c| c.len() < frame_count) {
+            channel.resize(frame_count, 0.0);
+        }
+    }
+}
+
+impl<S: Source<Item = f32>> Iterator for StretchedSource<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.output_pos >= self.output_len && !self.refill() {
+            return None;
+        }
+        let sample = self.output[self.channel][self.output_pos];
+        self.channel += 1;
+        if self.channel == self.output.len() {
+            self.channel = 0;
+            self.output_pos += 1;
+        }
+        Some(sample)
+    }
+}
+
+impl<S: Source<Item = f32>> Source for StretchedSource<S> {
+    fn current_span_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> ChannelCount {
+        self.stream.num_channels() as ChannelCount
+    }
+
+    fn sample_rate(&self) -> SampleRate {
+        self.stream.output_sample_rate() as SampleRate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        // depends on the speed, which may change while playing
+        None
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use ::rodio::buffer::SamplesBuffer;
+
+    use super::*;
+
+    #[test]
+    fn stretched_source() {
+        const SAMPLE_RATE: u32 = 44100;
+        const FRAME_COUNT: usize = 10000;
+
+        let samples = (0..FRAME_COUNT * 2)
+            .map(|i| (i / 2) as f32 / FRAME_COUNT as f32)
+            .collect::<Vec<_>>();
+        for speed in [0.5, 1.0, 1.5] {
+            let source = SamplesBuffer::new(2, SAMPLE_RATE, samples.clone());
+            let stretched = StretchedSource::new(source).unwrap();
+            assert_eq!(stretched.channels(), 2);
+            assert_eq!(stretched.sample_rate(), SAMPLE_RATE);
+            stretched.set_speed(speed).unwrap();
+            assert_eq!(stretched.controller().speed(), speed);
+
+            let output = stretched.collect::<Vec<_>>();
+            let expected_frames = (FRAME_COUNT as f64 / speed).round() as usize;
+            assert_eq!(output.len(), expected_frames * 2);
+            assert!(output.iter().all(|s| s.is_finite()));
+        }
+
+        // incomplete trailing frames get padded
+        let source = SamplesBuffer::new(2, SAMPLE_RATE, samples[..samples.len() - 1].to_vec());
+        let stretched = StretchedSource::new(source).unwrap();
+        assert_eq!(stretched.count(), FRAME_COUNT * 2);
+
+        assert!(
+            StretchedSource::new(SamplesBuffer::new(2, SAMPLE_RATE, samples))
+                .unwrap()
+                .set_pitch(0.0)
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,414 @@
+//! Input audio sources which feed the stretcher's grains on demand.
+
+use crate::Error;
+
+// -------------------------------------------------------------------------------------------------
+
+/// A source of input audio with random access, which is read by the grain drivers in this crate
+/// whenever the [`Stretcher`](crate::Stretcher) requests an input chunk.
+///
+/// Drivers only read frames within `0..len()`: regions of input chunks which lie outside of the
+/// source get muted in the stretcher instead.
+pub trait AudioSource {
+    /// Number of frames in the source.
+    fn len(&self) -> usize;
+
+    /// Returns true if the source contains no frames.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of channels in the source.
+    fn channels(&self) -> usize;
+
+    /// Reads frames `begin..end` into planar `output`: the nth channel's frames start at
+    /// `output[n * channel_stride]`. `begin..end` is guaranteed to be within `0..len()`.
+    fn read(&mut self, begin: usize, end: usize, output: &mut [f32], channel_stride: usize);
+}
+
+impl<S: AudioSource + ?Sized> AudioSource for &mut S {
+    fn len(&self) -> usize {
+        (**self).len()
+    }
+
+    fn channels(&self) -> usize {
+        (**self).channels()
+    }
+
+    fn read(&mut self, begin: usize, end: usize, output: &mut [f32], channel_stride: usize) {
+        (**self).read(begin, end, output, channel_stride)
+    }
+}
+
+impl<S: AudioSource + ?Sized> AudioSource for Box<S> {
+    fn len(&self) -> usize {
+        (**self).len()
+    }
+
+    fn channels(&self) -> usize {
+        (**self).channels()
+    }
+
+    fn read(&mut self, begin: usize, end: usize, output: &mut [f32], channel_stride: usize) {
+        (**self).read(begin, end, output, channel_stride)
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+// Planar in-memory sources: channels may be owned (`Vec<f32>`), shared (`Arc<[f32]>`) or
+// borrowed (`&[f32]`). The source's length is the length of the shortest channel.
+
+fn read_planar<C: AsRef<[f32]>>(
+    channels: &[C],
+    begin: usize,
+    end: usize,
+    output: &mut [f32],
+    channel_stride: usize,
+) {
+    for (channel, samples) in channels.iter().enumerate() {
+        output[channel * channel_stride..][..end - begin]
+            .copy_from_slice(&samples.as_ref()[begin..end]);
+    }
+}
+
+fn planar_len<C: AsRef<[f32]>>(channels: &[C]) -> usize {
+    channels.iter().map(|c| c.as_ref().len()).min().unwrap_or(0)
+}
+
+impl<C: AsRef<[f32]>> AudioSource for Vec<C> {
+    fn len(&self) -> usize {
+        planar_len(self)
+    }
+
+    fn channels(&self) -> usize {
+        self.as_slice().len()
+    }
+
+    fn read(&mut self, begin: usize, end: usize, output: &mut [f32], channel_stride: usize) {
+        read_planar(self, begin, end, output, channel_stride)
+    }
+}
+
+impl<C: AsRef<[f32]>> AudioSource for &[C] {
+    fn len(&self) -> usize {
+        planar_len(self)
+    }
+
+    fn channels(&self) -> usize {
+        <[C]>::len(self)
+    }
+
+    fn read(&mut self, begin: usize, end: usize, output: &mut [f32], channel_stride: usize) {
+        read_planar(self, begin, end, output, channel_stride)
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+/// An in-memory source of interleaved audio frames.
+#[derive(Debug, Clone)]
+pub struct InterleavedSource<B> {
+    data: B,
+    channels: usize,
+}
+
+impl<B: AsRef<[f32]>> InterleavedSource<B> {
+    /// Creates a new source from interleaved samples with the given channel count. A trailing
+    /// partial frame is ignored.
+    ///
+    /// # Errors
+    /// Returns an error if the channel count is zero.
+    pub fn new(data: B, channels: usize) -> Result<Self, Error> {
+        if channels == 0 {
+            return Err(Error::InvalidChannelCount(channels));
+        }
+        Ok(Self { data, channels })
+    }
+
+    /// Returns the wrapped interleaved samples.
+    pub fn into_inner(self) -> B {
+        self.data
+    }
+}
+
+impl<B: AsRef<[f32]>> AudioSource for InterleavedSource<B> {
+    fn len(&self) -> usize {
+        self.data.as_ref().len() / self.channels
+    }
+
+    fn channels(&self) -> usize {
+        self.channels
+    }
+
+    fn read(&mut self, begin: usize, end: usize, output: &mut [f32], channel_stride: usize) {
+        let frames = &self.data.as_ref()[begin * self.channels..end * self.channels];
+        for channel in 0..self.channels {
+            let output = &mut output[channel * channel_stride..][..end - begin];
+            for (o, frame) in output.iter_mut().zip(frames.chunks_exact(self.channels)) {
+                *o = frame[channel];
+            }
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(feature = "mmap")]
+pub use mapped::MappedSource;
+
+#[cfg(feature = "mmap")]
+mod mapped {
+    use std::{fs::File, io, path::Path};
+
+    use memmap2::Mmap;
+
+    use super::AudioSource;
+
+    /// WAVE_FORMAT_EXTENSIBLE format tag, which defines the sample format in a sub format GUID.
+    const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+    /// WAVE_FORMAT_IEEE_FLOAT format tag.
+    const WAVE_FORMAT_IEEE_FLOAT: u16 = 3;
+    /// KSDATAFORMAT_SUBTYPE_IEEE_FLOAT sub format GUID, as stored in extensible fmt chunks.
+    const KSDATAFORMAT_SUBTYPE_IEEE_FLOAT: [u8; 16] = [
+        0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xAA, 0x00, 0x38, 0x9B,
+        0x71,
+    ];
+
+    /// A memory-mapped file of interleaved 32-bit little-endian float samples, such as a raw
+    /// sample dump or the data chunk of a 32-bit float WAV file. Audio is paged in by the OS on
+    /// demand, so arbitrarily long files can be stretched without loading them into memory.
+    #[derive(Debug)]
+    pub struct MappedSource {
+        map: Mmap,
+        offset: usize,
+        len: usize,
+        channels: usize,
+    }
+
+    impl MappedSource {
+        /// Maps a raw file of interleaved `f32` samples, starting at the given byte offset.
+        pub fn open_raw(
+            path: impl AsRef<Path>,
+            channels: usize,
+            offset: usize,
+        ) -> io::Result<Self> {
+            if channels == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "invalid channel count",
+                ));
+            }
+            let map = Self::map(path.as_ref())?;
+            let offset = offset.min(map.len());
+            let len = (map.len() - offset) / (channels * 4);
+            Ok(Self {
+                map,
+                offset,
+                len,
+                channels,
+            })
+        }
+
+        /// Maps the data chunk of a 32-bit float WAV file.
+        pub fn open_wav(path: impl AsRef<Path>) -> io::Result<Self> {
+            let invalid = |message| io::Error::new(io::ErrorKind::InvalidData, message);
+            let map = Self::map(path.as_ref())?;
+            if map.len() < 12 || &map[0..4] != b"RIFF" || &map[8..12] != b"WAVE" {
+                return Err(invalid("not a WAV file"));
+            }
+
+            let mut channels = None;
+            let mut pos = 12;
+            while pos + 8 <= map.len() {
+                let id = &map[pos..pos + 4];
+                let size = u32::from_le_bytes(map[pos + 4..pos + 8].try_into().unwrap()) as usize;
+                let body = pos + 8;
+                match id {
+                    b"fmt " if size >= 16 && body + 16 <= map.len() => {
+                        let fmt = &map[body..(body + size).min(map.len())];
+                        let read_u16 =
+                            |offset: usize| u16::from_le_bytes([fmt[offset], fmt[offset + 1]]);
+                        let is_float = match read_u16(0) {
+                            WAVE_FORMAT_IEEE_FLOAT => true,
+                            // cbSize of at least 22 bytes, followed by valid bits, channel
+                            // mask and the sub format GUID
+                            WAVE_FORMAT_EXTENSIBLE => {
+                                fmt.len() >= 40
+                                    && read_u16(16) >= 22
+                                    && fmt[24..40] == KSDATAFORMAT_SUBTYPE_IEEE_FLOAT
+                            }
+                            _ => false,
+                        };
+                        if !is_float || read_u16(14) != 32 {
+                            return Err(invalid("only 32-bit float WAV files can be mapped"));
+                        }
+                        channels = Some(read_u16(2));
+                    }
+                    b"data" => {
+                        let channels = channels
+                            .filter(|c| *c > 0)
+                            .ok_or_else(|| invalid("missing or invalid fmt chunk"))?
+                            as usize;
+                        let size = size.min(map.len() - body);
+                        return Ok(Self {
+                            map,
+                            offset: body,
+                            len: size / (channels * 4),
+                            channels,
+                        });
+                    }
+                    _ => (),
+                }
+                // chunks are padded to an even size
+                pos = body + size + (size & 1);
+            }
+            Err(invalid("missing data chunk"))
+        }
+
+        fn map(path: &Path) -> io::Result<Mmap> {
+            let file = File::open(path)?;
+            // Safety: the file must not be modified while mapped, which is the caller's
+            // responsibility, just like with any other memory-mapped file.
+            unsafe { Mmap::map(&file) }
+        }
+    }
+
+    impl AudioSource for MappedSource {
+        fn len(&self) -> usize {
+            self.len
+        }
+
+        fn channels(&self) -> usize {
+            self.channels
+        }
+
+        fn read(&mut self, begin: usize, end: usize, output: &mut [f32], channel_stride: usize) {
+            let frame_size = self.channels * 4;
+            let bytes = &self.map[self.offset + begin * frame_size..self.offset + end * frame_size];
+            for (frame, bytes) in bytes.chunks_exact(frame_size).enumerate() {
+                for (channel, bytes) in bytes.chunks_exact(4).enumerate() {
+                    output[channel * channel_stride + frame] =
+                        f32::from_le_bytes(bytes.try_into().unwrap());
+                }
+            }
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_all(source: &mut impl AudioSource, begin: usize, end: usize) -> Vec<Vec<f32>> {
+        let stride = end - begin;
+        let mut output = vec![0.0; stride * source.channels()];
+        source.read(begin, end, &mut output, stride);
+        output.chunks(stride).map(|c| c.to_vec()).collect()
+    }
+
+    #[test]
+    fn planar_and_interleaved_sources() {
+        let left = vec![0.0, 1.0, 2.0, 3.0];
+        let right = vec![10.0, 11.0, 12.0];
+
+        let mut planar = vec![left.as_slice(), right.as_slice()];
+        assert_eq!(AudioSource::len(&planar), 3);
+        assert_eq!(AudioSource::channels(&planar), 2);
+        assert_eq!(
+            read_all(&mut planar, 1, 3),
+            [vec![1.0, 2.0], vec![11.0, 12.0]]
+        );
+
+        let mut interleaved =
+            InterleavedSource::new(vec![0.0, 10.0, 1.0, 11.0, 2.0, 12.0, 3.0], 2).unwrap();
+        assert_eq!(interleaved.len(), 3);
+        assert_eq!(interleaved.channels(), 2);
+        assert_eq!(
+            read_all(&mut interleaved, 1, 3),
+            [vec![1.0, 2.0], vec![11.0, 12.0]]
+        );
+
+        assert!(matches!(
+            InterleavedSource::new(vec![0.0f32; 4], 0),
+            Err(Error::InvalidChannelCount(0))
+        ));
+    }
+
+    /// A stereo 32-bit WAV file with the given format tag and fmt chunk extension.
+    #[cfg(feature = "mmap")]
+    fn wav_file(format: u16, extension: &[u8], data: &[u8]) -> Vec<u8> {
+        let fmt_size = 16 + extension.len() as u32;
+        let mut wav = Vec::new();
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&(20 + fmt_size + data.len() as u32).to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt ");
+        wav.extend_from_slice(&fmt_size.to_le_bytes());
+        wav.extend_from_slice(&format.to_le_bytes());
+        wav.extend_from_slice(&2u16.to_le_bytes()); // channels
+        wav.extend_from_slice(&44100u32.to_le_bytes());
+        wav.extend_from_slice(&(44100u32 * 8).to_le_bytes());
+        wav.extend_from_slice(&8u16.to_le_bytes());
+        wav.extend_from_slice(&32u16.to_le_bytes());
+        wav.extend_from_slice(extension);
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        wav.extend_from_slice(data);
+        wav
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn mapped_source() {
+        let samples = [0.0f32, 10.0, 1.0, 11.0, 2.0, 12.0];
+        let data = samples
+            .iter()
+            .flat_map(|s| s.to_le_bytes())
+            .collect::<Vec<_>>();
+
+        let path = std::env::temp_dir().join(format!("bungee-rs-{}.wav", std::process::id()));
+        std::fs::write(&path, wav_file(3, &[], &data)).unwrap();
+
+        let mut source = MappedSource::open_wav(&path).unwrap();
+        assert_eq!(source.len(), 3);
+        assert_eq!(source.channels(), 2);
+        assert_eq!(
+            read_all(&mut source, 1, 3),
+            [vec![1.0, 2.0], vec![11.0, 12.0]]
+        );
+
+        let mut raw = MappedSource::open_raw(&path, 2, 44).unwrap();
+        assert_eq!(raw.len(), 3);
+        assert_eq!(read_all(&mut raw, 0, 1), [vec![0.0], vec![10.0]]);
+
+        // extensible files are only mapped with an IEEE float sub format
+        let extension = |sub_format: u16| {
+            let mut extension = Vec::new();
+            extension.extend_from_slice(&22u16.to_le_bytes()); // cbSize
+            extension.extend_from_slice(&32u16.to_le_bytes()); // valid bits
+            extension.extend_from_slice(&3u32.to_le_bytes()); // channel mask
+            extension.extend_from_slice(&sub_format.to_le_bytes());
+            extension.extend_from_slice(&[
+                0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xAA, 0x00, 0x38, 0x9B, 0x71,
+            ]);
+            extension
+        };
+        std::fs::write(&path, wav_file(0xFFFE, &extension(3), &data)).unwrap();
+        let mut source = MappedSource::open_wav(&path).unwrap();
+        assert_eq!(source.len(), 3);
+        assert_eq!(read_all(&mut source, 2, 3), [vec![2.0], vec![12.0]]);
+
+        // 32-bit integer PCM
+        std::fs::write(&path, wav_file(0xFFFE, &extension(1), &data)).unwrap();
+        assert!(MappedSource::open_wav(&path).is_err());
+        std::fs::write(&path, wav_file(1, &[], &data)).unwrap();
+        assert!(MappedSource::open_wav(&path).is_err());
+        // truncated extension
+        std::fs::write(&path, wav_file(0xFFFE, &extension(3)[..8], &data)).unwrap();
+        assert!(MappedSource::open_wav(&path).is_err());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -2,59 +2,248 @@
 
 use bungee_sys::BungeeStream;
 
-use crate::Stretcher;
+use crate::{
+    control::{ControlBlock, StreamController, StreamControls},
+    edition::{Basic, EditionMarker},
+    realtime, Edition, Error, Stretcher, StretcherBuilder,
+};
+
+// -------------------------------------------------------------------------------------------------
+
+/// Builder for a [`Stream`], exposing all options of the underlying [`Stretcher`].
+#[derive(Debug, Clone)]
+pub struct StreamBuilder<E: EditionMarker = Basic> {
+    stretcher: StretcherBuilder<E>,
+    max_input_frame_count: usize,
+    max_output_frame_count: Option<usize>,
+    latency_compensation: bool,
+}
+
+impl StreamBuilder {
+    /// Creates a new builder for a Basic edition stream with equal input and output sample
+    /// rates, the default synthesis hop and instrumentation disabled. `max_input_frame_count` is
+    /// the maximum number of input frames passed to a single `Stream::process` call.
+    pub fn new(sample_rate: usize, num_channels: usize, max_input_frame_count: usize) -> Self {
+        Self {
+            stretcher: StretcherBuilder::new(sample_rate, num_channels),
+            max_input_frame_count,
+            max_output_frame_count: None,
+            latency_compensation: false,
+        }
+    }
+}
+
+impl<E: EditionMarker> StreamBuilder<E> {
+    /// Selects the Bungee edition of the stream's stretcher. See [`crate::edition`].
+    pub fn edition<F: EditionMarker>(self) -> StreamBuilder<F> {
+        StreamBuilder {
+            stretcher: self.stretcher.edition(),
+            max_input_frame_count: self.max_input_frame_count,
+            max_output_frame_count: self.max_output_frame_count,
+            latency_compensation: self.latency_compensation,
+        }
+    }
+
+    /// Sets the sample rate of the input audio.
+    pub fn input_sample_rate(mut self, sample_rate: usize) -> Self {
+        self.stretcher = self.stretcher.input_sample_rate(sample_rate);
+        self
+    }
+
+    /// Sets the sample rate of the output audio. See [`StretcherBuilder::output_sample_rate`].
+    pub fn output_sample_rate(mut self, sample_rate: usize) -> Self {
+        self.stretcher = self.stretcher.output_sample_rate(sample_rate);
+        self
+    }
+
+    /// Adjusts the synthesis hop size. See [`StretcherBuilder::log2_synthesis_hop_adjust`].
+    pub fn log2_synthesis_hop_adjust(mut self, log2_hop_adjust: i32) -> Self {
+        self.stretcher = self.stretcher.log2_synthesis_hop_adjust(log2_hop_adjust);
+        self
+    }
+
+    /// Enables verbose diagnostics and checks in the Bungee library.
+    pub fn instrumentation(mut self, enable: bool) -> Self {
+        self.stretcher = self.stretcher.instrumentation(enable);
+        self
+    }
+
+    /// Sets the maximum number of output frames which get rendered by a single
+    /// `Stream::process_interleaved` call, or a single block of `Stream::finish`. Interleaved
+    /// processing renders into internal scratch buffers of this size, so it never allocates.
+    ///
+    /// Defaults to the output of `max_input_frame_count` frames at speed 1.0. Slowing down
+    /// interleaved streams needs a larger value, e.g. `max_input_frame_count / min_speed` when
+    /// input and output sample rates are equal.
+    pub fn max_output_frame_count(mut self, max_output_frame_count: usize) -> Self {
+        self.max_output_frame_count = Some(max_output_frame_count);
+        self
+    }
+
+    /// Enables latency compensation: when enabled, the stream discards all output frames
+    /// which precede the first input frame, so the first rendered frame corresponds to the
+    /// start of the input, no matter how speed changes. Disabled by default, which renders the
+    /// stretcher's pre-roll as leading silence.
+    ///
+    /// Together with [`Stream::finish`], the total output length then matches the sum of the
+    /// requested output frame counts, e.g. `input_len / speed`, to the frame.
+    pub fn latency_compensation(mut self, enable: bool) -> Self {
+        self.latency_compensation = enable;
+        self
+    }
+
+    /// Creates and initializes the stream and its stretcher.
+    ///
+    /// # Errors
+    /// Returns an error if the stretcher options or the max input or output frame counts are
+    /// invalid, or if the stretcher or stream cannot be created.
+    pub fn build(self) -> Result<Stream<E>, Error> {
+        if self.max_input_frame_count == 0 {
+            return Err(Error::InvalidInputFrameCount(self.max_input_frame_count));
+        }
+        if self.max_output_frame_count == Some(0) {
+            return Err(Error::InvalidOutputFrameCount(0.0));
+        }
+        let stretcher = self.stretcher.build()?;
+        let max_output_frame_count = self.max_output_frame_count.unwrap_or_else(|| {
+            (self.max_input_frame_count as f64 * stretcher.output_sample_rate() as f64
+                / stretcher.input_sample_rate() as f64)
+                .ceil() as usize
+        });
+        Stream::with_stretcher(
+            stretcher,
+            self.max_input_frame_count,
+            max_output_frame_count,
+            self.latency_compensation,
+        )
+    }
+}
 
 // -------------------------------------------------------------------------------------------------
 
 /// A wrapper for `Stretcher` that provides an easy to use API for "streaming" applications
 /// where Bungee is used for forward playback only.
-pub struct Stream {
+///
+/// The stream uses the Bungee Basic edition by default: see [`crate::edition`] and
+/// [`StreamBuilder::edition`] for other editions.
+pub struct Stream<E: EditionMarker = Basic> {
     #[allow(dead_code)]
-    stretcher: Stretcher,
+    stretcher: Stretcher<E>,
     stream: *mut BungeeStream,
+    max_input_frame_count: usize,
+    max_output_frame_count: usize,
     input_pointers: Vec<*const f32>,
     output_pointers: Vec<*mut f32>,
+    input_scratch: Vec<f32>,
+    output_scratch: Vec<f32>,
+    controls: StreamControls,
+    latency_compensation: bool,
+    tail: OutputTail,
 }
 
-unsafe impl Send for Stream {}
-unsafe impl Sync for Stream {}
+unsafe impl<E: EditionMarker> Send for Stream<E> {}
+unsafe impl<E: EditionMarker> Sync for Stream<E> {}
 
 impl Stream {
-    /// Creates a new `Stream` instance from a stretcher instance.
+    /// Creates a new `Stream` instance with equal input and output sample rates and default
+    /// stretcher options. Use [`StreamBuilder`] to configure all options.
     pub fn new(
         sample_rate: usize,
         num_channels: usize,
         max_input_frame_count: usize,
-    ) -> Result<Self, &'static str> {
-        let stretcher = Stretcher::new(sample_rate, num_channels)?;
+    ) -> Result<Self, Error> {
+        StreamBuilder::new(sample_rate, num_channels, max_input_frame_count).build()
+    }
+}
 
-        let stream = bungee_sys::stream::create(
+impl<E: EditionMarker> Stream<E> {
+    /// Returns the stream's Bungee edition.
+    pub fn edition(&self) -> Edition {
+        E::EDITION
+    }
+
+    /// Creates a new `Stream` instance from a stretcher instance.
+    fn with_stretcher(
+        stretcher: Stretcher<E>,
+        max_input_frame_count: usize,
+        max_output_frame_count: usize,
+        latency_compensation: bool,
+    ) -> Result<Self, Error> {
+        let num_channels = stretcher.num_channels();
+        let stream = (E::FFI.stream_create)(
             stretcher.inner(),
             num_channels as i32,
             max_input_frame_count as i32,
         );
+        if stream.is_null() {
+            return Err(Error::CreationFailed("stream"));
+        }
 
         let input_pointers = vec![std::ptr::null(); num_channels];
         let output_pointers = vec![std::ptr::null_mut(); num_channels];
 
+        // planar scratch buffers for interleaved processing and finishing
+        let input_scratch = vec![0.0; max_input_frame_count * num_channels];
+        let output_scratch = vec![0.0; max_output_frame_count * num_channels];
+
+        let tail = OutputTail {
+            ratio: stretcher.output_sample_rate() as f64 / stretcher.input_sample_rate() as f64,
+            pitch: 1.0,
+            ..OutputTail::default()
+        };
+
         Ok(Stream {
             stream,
             stretcher,
+            max_input_frame_count,
+            max_output_frame_count,
             input_pointers,
             output_pointers,
+            input_scratch,
+            output_scratch,
+            controls: StreamControls::new(),
+            latency_compensation,
+            tail,
         })
     }
 
-    /// Returns the stretcher's sample rate.  
+    /// Returns the stretcher's input sample rate.  
     pub fn sample_rate(&self) -> usize {
         self.stretcher.sample_rate()
     }
 
+    /// Returns the stretcher's input sample rate.  
+    pub fn input_sample_rate(&self) -> usize {
+        self.stretcher.input_sample_rate()
+    }
+
+    /// Returns the stretcher's output sample rate.  
+    pub fn output_sample_rate(&self) -> usize {
+        self.stretcher.output_sample_rate()
+    }
+
     /// Returns the stretcher's channel layout.  
     pub fn num_channels(&self) -> usize {
         self.stretcher.num_channels()
     }
 
+    /// Returns the max number of input frames that can be passed to a single `process` call.
+    pub fn max_input_frame_count(&self) -> usize {
+        self.max_input_frame_count
+    }
+
+    /// Returns the max number of output frames that can be rendered by a single
+    /// `process_interleaved` call. See [`StreamBuilder::max_output_frame_count`].
+    pub fn max_output_frame_count(&self) -> usize {
+        self.max_output_frame_count
+    }
+
+    /// Returns true if the stream discards its pre-roll output.
+    /// See [`StreamBuilder::latency_compensation`].
+    pub fn latency_compensation(&self) -> bool {
+        self.latency_compensation
+    }
+
     /// Processes a segment of audio. Returns the number of output frames that were rendered
     /// to `output_channels`.
     /// The number of frames will be set by dithering either to `floor(output_frame_count)` or
@@ -65,7 +254,12 @@ impl Stream {
     ///   set to `None` for mute input
     /// * **output_channels:** Slice of `Vec<f32>`, one for each channel of output audio
     /// * **input_frame_count:** Number of input audio frames to be processed
+    /// * **output_frame_count:** Number of output audio frames to be rendered, in output sample
+    ///   rate frames
     /// * **pitch:** Audio pitch shift (see Request::pitch)
+    ///
+    /// # Panics
+    /// Panics if any of the arguments is invalid. See [`Self::try_process`].
     pub fn process(
         &mut self,
         input_channels: Option<&[Vec<f32>]>,
@@ -74,106 +268,540 @@ impl Stream {
         output_frame_count: f64,
         pitch: f64,
     ) -> usize {
-        // verify input/output frame counts
-        assert!(
-            input_frame_count > 0,
-            "invalid input frame count: got {input_frame_count} frames, but need frames > 0"
-        );
-        assert!(
-            output_frame_count > 0.0,
-            "invalid output frame count: got {output_frame_count} frames, but need frames > 0"
-        );
+        self.try_process(
+            input_channels,
+            output_channels,
+            input_frame_count,
+            output_frame_count,
+            pitch,
+        )
+        .unwrap_or_else(|err| panic!("{err}"))
+    }
 
-        // verify pitch
-        assert!(
-            pitch > 0.0,
-            "invalid pitch: pitch must be > 0 but is '{pitch}'"
-        );
+    /// Processes a segment of audio, just like [`Self::process`], but returns an error
+    /// instead of panicking when an argument is invalid.
+    ///
+    /// # Errors
+    /// Returns an error if the frame counts or pitch are invalid, if the number of channel
+    /// buffers doesn't match the stream's channel count or if a channel buffer is too small.
+    pub fn try_process(
+        &mut self,
+        input_channels: Option<&[Vec<f32>]>,
+        output_channels: &mut [Vec<f32>],
+        input_frame_count: usize,
+        output_frame_count: f64,
+        pitch: f64,
+    ) -> Result<usize, Error> {
+        self.try_process_planar(
+            input_channels,
+            output_channels,
+            input_frame_count,
+            output_frame_count,
+            pitch,
+        )
+    }
 
-        // verify input data constraints and convert to ptrs
-        if let Some(inputs) = input_channels {
-            assert_eq!(
-                inputs.len(),
-                self.num_channels(),
-                "input_channels slice count must match stream channel count"
-            );
-            for (channel, samples) in inputs.iter().enumerate() {
-                assert!(
-                    samples.len() >= input_frame_count,
-                    "input channel[{}].len() ({}) is less than input_frame_count ({})",
-                    channel,
-                    samples.len(),
-                    input_frame_count
-                );
+    /// Processes a segment of planar audio, just like [`Self::process`], but accepts any
+    /// channel buffer type, such as `&[f32]` slices into a contiguous buffer.
+    ///
+    /// # Panics
+    /// Panics if any of the arguments is invalid. See [`Self::try_process_planar`].
+    pub fn process_planar<I: AsRef<[f32]>, O: AsMut<[f32]>>(
+        &mut self,
+        input_channels: Option<&[I]>,
+        output_channels: &mut [O],
+        input_frame_count: usize,
+        output_frame_count: f64,
+        pitch: f64,
+    ) -> usize {
+        self.try_process_planar(
+            input_channels,
+            output_channels,
+            input_frame_count,
+            output_frame_count,
+            pitch,
+        )
+        .unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Processes a segment of planar audio, just like [`Self::process_planar`], but returns
+    /// an error instead of panicking when an argument is invalid.
+    ///
+    /// # Errors
+    /// See [`Self::try_process`].
+    pub fn try_process_planar<I: AsRef<[f32]>, O: AsMut<[f32]>>(
+        &mut self,
+        input_channels: Option<&[I]>,
+        output_channels: &mut [O],
+        input_frame_count: usize,
+        output_frame_count: f64,
+        pitch: f64,
+    ) -> Result<usize, Error> {
+        realtime::guard(|| {
+            self.validate_arguments(input_frame_count, output_frame_count, pitch)?;
+
+            // verify input data constraints
+            if let Some(inputs) = input_channels {
+                self.validate_channels(inputs.iter().map(|c| c.as_ref().len()), input_frame_count)?;
             }
-        }
-        if let Some(input_channels) = input_channels {
-            for (p, c) in self.input_pointers.iter_mut().zip(input_channels) {
-                *p = c.as_ptr();
+
+            // verify output data constraints
+            let required_output_len = output_frame_count.ceil() as usize;
+            self.validate_channels(
+                output_channels.iter_mut().map(|c| c.as_mut().len()),
+                required_output_len,
+            )?;
+
+            // convert to ptrs
+            if let Some(input_channels) = input_channels {
+                for (p, c) in self.input_pointers.iter_mut().zip(input_channels) {
+                    *p = c.as_ref().as_ptr();
+                }
+            }
+            for (p, c) in self.output_pointers.iter_mut().zip(output_channels) {
+                *p = c.as_mut().as_mut_ptr();
             }
-        } else {
-            self.input_pointers.fill(std::ptr::null());
-        }
 
-        // verify output data constraints and convert to ptrs
-        assert_eq!(
-            output_channels.len(),
-            self.num_channels(),
-            "output_channels slice count must match stream channel count"
-        );
-        let required_output_len = output_frame_count.ceil() as usize;
-        for (channel, samples) in output_channels.iter().enumerate() {
-            assert!(
-                samples.len() >= required_output_len,
-                "output channel[{}].len() ({}) is less than required output frame count ({})",
-                channel,
-                samples.len(),
-                required_output_len
+            // process
+            Ok(self.process_pointers(
+                input_channels.is_some(),
+                input_frame_count,
+                output_frame_count,
+                pitch,
+            ))
+        })
+    }
+
+    /// Processes a segment of interleaved audio, just like [`Self::process`]. Audio gets
+    /// de- and interleaved via internal scratch buffers, so this doesn't allocate. The output
+    /// frame count is limited to [`Self::max_output_frame_count`].
+    ///
+    /// Parameters:
+    /// * **input:** Interleaved input audio: set to `None` for mute input
+    /// * **output:** Interleaved output audio
+    /// * **input_frame_count:** Number of input audio frames to be processed
+    /// * **output_frame_count:** Number of output audio frames to be rendered, in output sample
+    ///   rate frames
+    /// * **pitch:** Audio pitch shift (see Request::pitch)
+    ///
+    /// # Panics
+    /// Panics if any of the arguments is invalid. See [`Self::try_process_interleaved`].
+    pub fn process_interleaved(
+        &mut self,
+        input: Option<&[f32]>,
+        output: &mut [f32],
+        input_frame_count: usize,
+        output_frame_count: f64,
+        pitch: f64,
+    ) -> usize {
+        self.try_process_interleaved(input, output, input_frame_count, output_frame_count, pitch)
+            .unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Processes a segment of interleaved audio, just like [`Self::process_interleaved`],
+    /// but returns an error instead of panicking when an argument is invalid.
+    ///
+    /// # Errors
+    /// See [`Self::try_process`]. For interleaved buffers, `Error::BufferTooSmall` reports
+    /// frame counts with a channel index of 0. Returns `Error::InvalidOutputFrameCount` when
+    /// the output frame count exceeds [`Self::max_output_frame_count`].
+    pub fn try_process_interleaved(
+        &mut self,
+        input: Option<&[f32]>,
+        output: &mut [f32],
+        input_frame_count: usize,
+        output_frame_count: f64,
+        pitch: f64,
+    ) -> Result<usize, Error> {
+        realtime::guard(|| {
+            self.validate_arguments(input_frame_count, output_frame_count, pitch)?;
+
+            let num_channels = self.num_channels();
+            let required_output_len = output_frame_count.ceil() as usize;
+            if required_output_len > self.max_output_frame_count {
+                return Err(Error::InvalidOutputFrameCount(output_frame_count));
+            }
+
+            // verify interleaved data constraints
+            let interleaved_frame_counts = [
+                (
+                    input.map_or(input_frame_count, |i| i.len() / num_channels),
+                    input_frame_count,
+                ),
+                (output.len() / num_channels, required_output_len),
+            ];
+            for (len, required) in interleaved_frame_counts {
+                if len < required {
+                    return Err(Error::BufferTooSmall {
+                        channel: 0,
+                        len,
+                        required,
+                    });
+                }
+            }
+
+            // deinterleave input into planar scratch buffers
+            if let Some(input) = input {
+                let stride = self.max_input_frame_count;
+                for (channel, p) in self.input_pointers.iter_mut().enumerate() {
+                    let scratch = &mut self.input_scratch[channel * stride..][..input_frame_count];
+                    for (s, frame) in scratch.iter_mut().zip(input.chunks_exact(num_channels)) {
+                        *s = frame[channel];
+                    }
+                    *p = scratch.as_ptr();
+                }
+            }
+
+            // set up planar output scratch buffers
+            let stride = self.max_output_frame_count;
+            for (channel, p) in self.output_pointers.iter_mut().enumerate() {
+                *p = self.output_scratch[channel * stride..].as_mut_ptr();
+            }
+
+            // process
+            let frame_count = self.process_pointers(
+                input.is_some(),
+                input_frame_count,
+                output_frame_count,
+                pitch,
             );
+
+            // interleave planar output
+            for (channel, scratch) in self.output_scratch.chunks_exact(stride).enumerate() {
+                for (frame, s) in output
+                    .chunks_exact_mut(num_channels)
+                    .zip(&scratch[..frame_count])
+                {
+                    frame[channel] = *s;
+                }
+            }
+
+            Ok(frame_count)
+        })
+    }
+
+    /// Returns a new handle which controls the speed and pitch of
+    /// [`Self::process_controlled`] calls from other threads. All handles of a stream share the
+    /// same parameters.
+    pub fn controller(&self) -> StreamController {
+        self.controls.controller()
+    }
+
+    /// Processes a segment of planar audio with the speed and pitch of the stream's
+    /// [`StreamController`], which are applied at the start of each call.
+    ///
+    /// The number of output frames is derived from the input frame count, the controller's
+    /// speed and the stream's sample rates, so output channels must hold at least
+    /// `ceil(input_frame_count / speed * output_sample_rate / input_sample_rate)` frames for
+    /// the slowest speed that is used.
+    ///
+    /// This does not allocate, so it can be called on real-time audio threads while other
+    /// threads change the controller's parameters.
+    ///
+    /// # Panics
+    /// Panics if any of the arguments is invalid. See [`Self::try_process_controlled`].
+    pub fn process_controlled<I: AsRef<[f32]>, O: AsMut<[f32]>>(
+        &mut self,
+        input_channels: Option<&[I]>,
+        output_channels: &mut [O],
+        input_frame_count: usize,
+    ) -> usize {
+        self.try_process_controlled(input_channels, output_channels, input_frame_count)
+            .unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Processes a segment of planar audio with the speed and pitch of the stream's
+    /// [`StreamController`], just like [`Self::process_controlled`], but returns an error
+    /// instead of panicking when an argument is invalid.
+    ///
+    /// # Errors
+    /// See [`Self::try_process`].
+    pub fn try_process_controlled<I: AsRef<[f32]>, O: AsMut<[f32]>>(
+        &mut self,
+        input_channels: Option<&[I]>,
+        output_channels: &mut [O],
+        input_frame_count: usize,
+    ) -> Result<usize, Error> {
+        realtime::guard(|| {
+            let block = self.next_controlled_block(input_frame_count);
+            // the controls only move on once all arguments were accepted
+            let frame_count = self.try_process_planar(
+                input_channels,
+                output_channels,
+                input_frame_count,
+                block.output_frame_count,
+                block.pitch,
+            )?;
+            self.commit_controlled_block(block);
+            Ok(frame_count)
+        })
+    }
+
+    /// Returns the number of output frames which [`Self::finish`] will render: the part of the
+    /// requested output which is still buffered in the stretcher.
+    pub fn finish_frame_count(&self) -> usize {
+        let mut target = self.tail.requested.round() as usize;
+        if !self.latency_compensation {
+            target += self.tail.preroll;
         }
-        for (p, c) in self.output_pointers.iter_mut().zip(output_channels) {
-            *p = c.as_mut_ptr();
-        }
+        target.saturating_sub(self.tail.rendered)
+    }
 
-        // process
-        bungee_sys::stream::process(
-            self.stream,
-            if input_channels.is_none() {
-                std::ptr::null()
-            } else {
-                self.input_pointers.as_ptr()
-            },
-            self.output_pointers.as_mut_ptr(),
-            input_frame_count as i32,
+    /// Drains the stream at the end of the input: renders the remaining, buffered output with
+    /// mute input, using the speed and pitch of the last process call. Returns the number of
+    /// frames that were rendered to `output_channels`, which is [`Self::finish_frame_count`].
+    ///
+    /// Afterwards the sum of all rendered frames equals the sum of the requested output frame
+    /// counts, plus the pre-roll when latency compensation is disabled. The stream should not
+    /// be processed further after finishing it.
+    ///
+    /// # Panics
+    /// Panics if any of the arguments is invalid. See [`Self::try_finish`].
+    pub fn finish<O: AsMut<[f32]>>(&mut self, output_channels: &mut [O]) -> usize {
+        self.try_finish(output_channels)
+            .unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Drains the stream at the end of the input, just like [`Self::finish`], but returns an
+    /// error instead of panicking when an argument is invalid.
+    ///
+    /// # Errors
+    /// Returns an error if the number of channel buffers doesn't match the stream's channel
+    /// count or if a channel buffer can't hold [`Self::finish_frame_count`] frames. Returns
+    /// `Error::InvalidOutputFrameCount` when the output of a single input frame at the last
+    /// speed exceeds [`Self::max_output_frame_count`].
+    pub fn try_finish<O: AsMut<[f32]>>(
+        &mut self,
+        output_channels: &mut [O],
+    ) -> Result<usize, Error> {
+        realtime::guard(|| {
+            let remaining = self.finish_frame_count();
+            self.validate_channels(
+                output_channels.iter_mut().map(|c| c.as_mut().len()),
+                remaining,
+            )?;
+
+            // render flush blocks into the planar output scratch buffers, as the last block
+            // usually renders more frames than required
+            let ratio = self.tail.ratio;
+            let stride = self.max_output_frame_count;
+            let input_frame_count =
+                ((stride as f64 / ratio).floor() as usize).clamp(1, self.max_input_frame_count);
+            let output_frame_count = input_frame_count as f64 * ratio;
+            if remaining > 0 && output_frame_count.ceil() as usize > stride {
+                return Err(Error::InvalidOutputFrameCount(output_frame_count));
+            }
+
+            let mut written = 0;
+            while written < remaining {
+                for (channel, p) in self.output_pointers.iter_mut().enumerate() {
+                    *p = self.output_scratch[channel * stride..].as_mut_ptr();
+                }
+                let frame_count = self
+                    .render(
+                        false,
+                        input_frame_count,
+                        output_frame_count,
+                        self.tail.pitch,
+                    )
+                    .min(remaining - written);
+                for (output, scratch) in output_channels
+                    .iter_mut()
+                    .zip(self.output_scratch.chunks_exact(stride))
+                {
+                    output.as_mut()[written..written + frame_count]
+                        .copy_from_slice(&scratch[..frame_count]);
+                }
+                written += frame_count;
+            }
+            self.tail.rendered += written;
+            Ok(written)
+        })
+    }
+
+    /// Returns the output frame count and pitch of the next controlled block of
+    /// `input_frame_count` frames. The stream's controls only move on once the block gets
+    /// [committed](Self::commit_controlled_block).
+    pub(crate) fn next_controlled_block(&self, input_frame_count: usize) -> ControlledBlock {
+        let controls = self.controls.next_block(input_frame_count);
+        let output_frame_count = input_frame_count as f64 / controls.speed()
+            * self.output_sample_rate() as f64
+            / self.input_sample_rate() as f64;
+        ControlledBlock {
             output_frame_count,
-            pitch,
-        ) as usize
+            pitch: controls.pitch(),
+            controls,
+        }
+    }
+
+    /// Moves the stream's controls on after a block of [`Self::next_controlled_block`] was
+    /// processed.
+    pub(crate) fn commit_controlled_block(&mut self, block: ControlledBlock) {
+        self.controls.commit(block.controls);
     }
 
     /// Current position in the input stream. This is sum of `input_sample_count` over all `process()` calls.
     pub fn input_position(&self) -> isize {
-        bungee_sys::stream::input_position(self.stream) as isize
+        realtime::guard(|| (E::FFI.stream_input_position)(self.stream) as isize)
     }
 
     /// Current position of the output stream in terms of input frames.
     pub fn output_position(&self) -> f64 {
-        bungee_sys::stream::output_position(self.stream)
+        realtime::guard(|| (E::FFI.stream_output_position)(self.stream))
     }
 
     /// Latency due to the stretcher. Units are input frames.
     pub fn latency(&self) -> f64 {
-        bungee_sys::stream::latency(self.stream)
+        realtime::guard(|| (E::FFI.stream_latency)(self.stream))
+    }
+
+    /// Verifies the frame counts and pitch of a process call.
+    fn validate_arguments(
+        &self,
+        input_frame_count: usize,
+        output_frame_count: f64,
+        pitch: f64,
+    ) -> Result<(), Error> {
+        // verify input/output frame counts
+        if input_frame_count == 0 || input_frame_count > self.max_input_frame_count {
+            return Err(Error::InvalidInputFrameCount(input_frame_count));
+        }
+        if !(output_frame_count.is_finite() && output_frame_count > 0.0) {
+            return Err(Error::InvalidOutputFrameCount(output_frame_count));
+        }
+
+        // verify pitch
+        if !(pitch.is_finite() && pitch > 0.0) {
+            return Err(Error::InvalidPitch(pitch));
+        }
+        Ok(())
+    }
+
+    /// Verifies the channel count and length of planar channel buffers.
+    fn validate_channels(
+        &self,
+        channel_lengths: impl ExactSizeIterator<Item = usize>,
+        required_len: usize,
+    ) -> Result<(), Error> {
+        if channel_lengths.len() != self.num_channels() {
+            return Err(Error::ChannelMismatch {
+                expected: self.num_channels(),
+                actual: channel_lengths.len(),
+            });
+        }
+        for (channel, len) in channel_lengths.enumerate() {
+            if len < required_len {
+                return Err(Error::BufferTooSmall {
+                    channel,
+                    len,
+                    required: required_len,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs the FFI stream with the current input and output pointers and keeps track of the
+    /// requested and rendered output for [`Self::finish`].
+    fn process_pointers(
+        &mut self,
+        has_input: bool,
+        input_frame_count: usize,
+        output_frame_count: f64,
+        pitch: f64,
+    ) -> usize {
+        let frame_count = self.render(has_input, input_frame_count, output_frame_count, pitch);
+        self.tail.requested += output_frame_count;
+        self.tail.rendered += frame_count;
+        self.tail.ratio = output_frame_count / input_frame_count as f64;
+        self.tail.pitch = pitch;
+        frame_count
+    }
+
+    /// Runs the FFI stream with the current input and output pointers. With latency
+    /// compensation, output frames which precede the first input frame get dropped from the
+    /// start of the output buffers.
+    fn render(
+        &mut self,
+        has_input: bool,
+        input_frame_count: usize,
+        output_frame_count: f64,
+        pitch: f64,
+    ) -> usize {
+        let input_pointers = if has_input {
+            self.input_pointers.as_ptr()
+        } else {
+            std::ptr::null()
+        };
+        let start_position = self.output_position();
+        let frame_count = (E::FFI.stream_process)(
+            self.stream,
+            input_pointers,
+            self.output_pointers.as_mut_ptr(),
+            input_frame_count as i32,
+            output_frame_count,
+            pitch,
+        ) as usize;
+
+        // number of rendered frames which precede the first input frame
+        let end_position = self.output_position();
+        let preroll = if end_position <= 0.0 {
+            frame_count
+        } else if start_position >= 0.0 {
+            0
+        } else {
+            let fraction = -start_position / (end_position - start_position);
+            ((frame_count as f64 * fraction).round() as usize).min(frame_count)
+        };
+        if preroll == 0 {
+            return frame_count;
+        }
+        if !self.latency_compensation {
+            self.tail.preroll += preroll;
+            return frame_count;
+        }
+        for p in &self.output_pointers {
+            // Safety: output pointers point to buffers of at least `frame_count` frames.
+            unsafe { std::ptr::copy(p.add(preroll), *p, frame_count - preroll) };
+        }
+        frame_count - preroll
     }
 }
 
-impl Drop for Stream {
+impl<E: EditionMarker> Drop for Stream<E> {
     fn drop(&mut self) {
-        bungee_sys::stream::destroy(self.stream);
+        (E::FFI.stream_destroy)(self.stream);
     }
 }
 
 // -------------------------------------------------------------------------------------------------
 
+/// Output bookkeeping of a [`Stream`], which is needed to drain it exactly.
+#[derive(Debug, Default, Clone, Copy)]
+struct OutputTail {
+    /// Sum of the requested output frame counts.
+    requested: f64,
+    /// Number of output frames returned to the caller.
+    rendered: usize,
+    /// Number of returned pre-roll frames, when latency compensation is disabled.
+    preroll: usize,
+    /// Output frames per input frame of the last process call.
+    ratio: f64,
+    /// Pitch of the last process call.
+    pitch: f64,
+}
+
+/// Output frame count and pitch of the next [`Stream::process_controlled`] block, together with
+/// the state of the stream's controls after it.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ControlledBlock {
+    pub output_frame_count: f64,
+    pub pitch: f64,
+    controls: ControlBlock,
+}
+
+// -------------------------------------------------------------------------------------------------
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -211,4 +839,282 @@ mod tests {
         assert_eq!(stream.input_position(), INPUT_SAMPLES_COUNT as isize);
         assert!(stream.latency() > 0.0);
     }
+
+    #[test]
+    fn stream_builder() {
+        let stream = StreamBuilder::new(44100, 2, 1024)
+            .output_sample_rate(48000)
+            .log2_synthesis_hop_adjust(1)
+            .build()
+            .unwrap();
+        assert_eq!(stream.input_sample_rate(), 44100);
+        assert_eq!(stream.output_sample_rate(), 48000);
+        assert_eq!(stream.num_channels(), 2);
+
+        assert!(matches!(
+            StreamBuilder::new(44100, 2, 0).build(),
+            Err(Error::InvalidInputFrameCount(0))
+        ));
+        assert!(matches!(
+            StreamBuilder::new(0, 2, 1024).build(),
+            Err(Error::InvalidSampleRate(0))
+        ));
+        assert!(matches!(
+            StreamBuilder::new(44100, 2, 1024)
+                .max_output_frame_count(0)
+                .build(),
+            Err(Error::InvalidOutputFrameCount(_))
+        ));
+
+        // the max output frame count follows the sample rates by default
+        assert_eq!(stream.max_output_frame_count(), 1115);
+    }
+
+    #[test]
+    fn stream_invalid_arguments() {
+        let mut stream = Stream::new(44100, 2, 1024).unwrap();
+
+        let input = vec![vec![0.0f32; 1024]; 2];
+        let mut output = vec![vec![0.0f32; 1024]; 2];
+        assert!(matches!(
+            stream.try_process(Some(&input), &mut output, 2048, 1024.0, 1.0),
+            Err(Error::InvalidInputFrameCount(2048))
+        ));
+        assert!(matches!(
+            stream.try_process(Some(&input), &mut output, 1024, 1024.0, 0.0),
+            Err(Error::InvalidPitch(_))
+        ));
+        assert!(matches!(
+            stream.try_process(Some(&input[..1]), &mut output, 1024, 1024.0, 1.0),
+            Err(Error::ChannelMismatch {
+                expected: 2,
+                actual: 1
+            })
+        ));
+        assert!(matches!(
+            stream.try_process(Some(&input), &mut output, 1024, 2048.0, 1.0),
+            Err(Error::BufferTooSmall {
+                channel: 0,
+                len: 1024,
+                required: 2048
+            })
+        ));
+        // nothing got processed
+        assert_eq!(stream.input_position(), 0);
+    }
+
+    #[test]
+    fn stream_planar_and_interleaved() {
+        const NUM_CHANNELS: usize = 2;
+        const FRAME_COUNT: usize = 512;
+
+        let mut planar_stream = Stream::new(44100, NUM_CHANNELS, FRAME_COUNT).unwrap();
+        let mut interleaved_stream = Stream::new(44100, NUM_CHANNELS, FRAME_COUNT).unwrap();
+
+        // planar input in one contiguous buffer
+        let input = (0..FRAME_COUNT * NUM_CHANNELS)
+            .map(|i| (i as f32 * 0.01).sin())
+            .collect::<Vec<_>>();
+        let input_channels = input.chunks_exact(FRAME_COUNT).collect::<Vec<_>>();
+        let interleaved_input = (0..FRAME_COUNT * NUM_CHANNELS)
+            .map(|i| input_channels[i % NUM_CHANNELS][i / NUM_CHANNELS])
+            .collect::<Vec<_>>();
+
+        let mut output = vec![0.0f32; FRAME_COUNT * NUM_CHANNELS];
+        let mut interleaved_output = vec![0.0f32; FRAME_COUNT * NUM_CHANNELS];
+        for _ in 0..8 {
+            let mut output_channels = output.chunks_exact_mut(FRAME_COUNT).collect::<Vec<_>>();
+            let planar_frames = planar_stream.process_planar(
+                Some(&input_channels),
+                &mut output_channels,
+                FRAME_COUNT,
+                FRAME_COUNT as f64,
+                1.0,
+            );
+            let interleaved_frames = interleaved_stream.process_interleaved(
+                Some(&interleaved_input),
+                &mut interleaved_output,
+                FRAME_COUNT,
+                FRAME_COUNT as f64,
+                1.0,
+            );
+            assert_eq!(planar_frames, interleaved_frames);
+            for frame in 0..planar_frames {
+                for channel in 0..NUM_CHANNELS {
+                    assert_eq!(
+                        output[channel * FRAME_COUNT + frame],
+                        interleaved_output[frame * NUM_CHANNELS + channel]
+                    );
+                }
+            }
+        }
+
+        // interleaved output is limited to the max output frame count
+        assert_eq!(interleaved_stream.max_output_frame_count(), FRAME_COUNT);
+        let mut slow_output = vec![0.0f32; FRAME_COUNT * 2 * NUM_CHANNELS];
+        assert!(matches!(
+            interleaved_stream.try_process_interleaved(
+                Some(&interleaved_input),
+                &mut slow_output,
+                FRAME_COUNT,
+                FRAME_COUNT as f64 * 2.0,
+                1.0,
+            ),
+            Err(Error::InvalidOutputFrameCount(_))
+        ));
+        let mut slow_stream = StreamBuilder::new(44100, NUM_CHANNELS, FRAME_COUNT)
+            .max_output_frame_count(FRAME_COUNT * 2)
+            .build()
+            .unwrap();
+        let (frame_count, allocations) = crate::test_alloc::count_allocations(|| {
+            slow_stream.process_interleaved(
+                Some(&interleaved_input),
+                &mut slow_output,
+                FRAME_COUNT,
+                FRAME_COUNT as f64 * 2.0,
+                1.0,
+            )
+        });
+        assert_eq!(frame_count, FRAME_COUNT * 2);
+        assert_eq!(allocations, 0);
+
+        // interleaved buffers are validated in frames
+        assert!(matches!(
+            interleaved_stream.try_process_interleaved(
+                Some(&interleaved_input[..FRAME_COUNT]),
+                &mut interleaved_output,
+                FRAME_COUNT,
+                FRAME_COUNT as f64,
+                1.0,
+            ),
+            Err(Error::BufferTooSmall {
+                channel: 0,
+                len: 256,
+                required: FRAME_COUNT
+            })
+        ));
+    }
+
+    #[test]
+    fn stream_controller() {
+        const FRAME_COUNT: usize = 512;
+        let mut stream = Stream::new(44100, 2, FRAME_COUNT).unwrap();
+        let controller = stream.controller();
+        controller.set_smoothing(FRAME_COUNT * 4);
+
+        let input = vec![vec![0.5f32; FRAME_COUNT]; 2];
+        let mut output = vec![vec![0.0f32; FRAME_COUNT * 4]; 2];
+
+        // change parameters from another thread while processing
+        let running = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(true));
+        let ui = std::thread::spawn({
+            let controller = controller.clone();
+            let running = running.clone();
+            move || {
+                let mut count = 0;
+                while running.load(std::sync::atomic::Ordering::Relaxed) {
+                    count += 1;
+                    controller
+                        .set_speed(0.5 + (count % 3) as f64 * 0.5)
+                        .unwrap();
+                    controller
+                        .set_pitch(0.5 + (count % 4) as f64 * 0.25)
+                        .unwrap();
+                    if count % 7 == 0 {
+                        controller.jump_to_targets();
+                    }
+                    std::thread::yield_now();
+                }
+            }
+        });
+
+        let (_, allocations) = crate::test_alloc::count_allocations(|| {
+            for _ in 0..500 {
+                let frame_count = stream.process_controlled(Some(&input), &mut output, FRAME_COUNT);
+                assert!(frame_count <= FRAME_COUNT * 2 + 1);
+            }
+        });
+        running.store(false, std::sync::atomic::Ordering::Relaxed);
+        ui.join().unwrap();
+        assert_eq!(allocations, 0);
+
+        // once settled, output frame counts follow the controller's speed
+        controller.set_speed(0.5).unwrap();
+        controller.jump_to_targets();
+        let frame_count = stream.process_controlled(Some(&input), &mut output, FRAME_COUNT);
+        assert!(frame_count.abs_diff(FRAME_COUNT * 2) <= 1);
+
+        // rejected calls don't advance the ramps
+        controller.set_speed(1.0).unwrap();
+        let mut short_output = vec![vec![0.0f32; FRAME_COUNT]; 2];
+        for _ in 0..4 {
+            assert!(matches!(
+                stream.try_process_controlled(Some(&input), &mut short_output, FRAME_COUNT),
+                Err(Error::BufferTooSmall { .. })
+            ));
+            assert!(matches!(
+                stream.try_process_controlled(Some(&input[..1]), &mut output, FRAME_COUNT),
+                Err(Error::ChannelMismatch { .. })
+            ));
+        }
+        // a quarter of the way from speed 0.5 to 1.0
+        let frame_count = stream.process_controlled(Some(&input), &mut output, FRAME_COUNT);
+        assert!(frame_count.abs_diff(FRAME_COUNT * 8 / 5) <= 1);
+    }
+
+    #[test]
+    fn stream_latency_compensation() {
+        const FRAME_COUNT: usize = 1000;
+        const SPEED: f64 = 0.75;
+
+        for compensation in [false, true] {
+            let mut stream = StreamBuilder::new(44100, 2, FRAME_COUNT)
+                .latency_compensation(compensation)
+                .build()
+                .unwrap();
+            assert_eq!(stream.latency_compensation(), compensation);
+
+            let input = vec![vec![0.5f32; FRAME_COUNT]; 2];
+            let mut output = vec![vec![0.0f32; FRAME_COUNT * 2]; 2];
+            let mut output_len = 0;
+            for _ in 0..10 {
+                output_len += stream.process(
+                    Some(&input),
+                    &mut output,
+                    FRAME_COUNT,
+                    FRAME_COUNT as f64 / SPEED,
+                    1.0,
+                );
+            }
+            // the pre-roll only gets rendered without compensation
+            let preroll = (stream.latency() / SPEED).round() as usize;
+            let expected_len = (FRAME_COUNT as f64 * 10.0 / SPEED).round() as usize;
+            if compensation {
+                assert_eq!(output_len + stream.finish_frame_count(), expected_len);
+            } else {
+                let len = output_len + stream.finish_frame_count();
+                assert!(len.abs_diff(expected_len + preroll) <= 1);
+            }
+
+            // finish drains the tail exactly
+            let mut tail = vec![vec![0.0f32; stream.finish_frame_count()]; 2];
+            let tail_len = stream.finish(&mut tail);
+            assert_eq!(tail_len, tail[0].len());
+            assert_eq!(stream.finish_frame_count(), 0);
+            assert_eq!(stream.finish(&mut tail), 0);
+        }
+
+        // output buffers must hold the tail
+        let mut stream = StreamBuilder::new(44100, 1, FRAME_COUNT)
+            .latency_compensation(true)
+            .build()
+            .unwrap();
+        let input = vec![vec![0.5f32; FRAME_COUNT]];
+        let mut output = vec![vec![0.0f32; FRAME_COUNT * 2]];
+        stream.process(Some(&input), &mut output, FRAME_COUNT, 2000.0, 1.0);
+        assert!(matches!(
+            stream.try_finish(&mut [vec![0.0f32; 10]]),
+            Err(Error::BufferTooSmall { .. })
+        ));
+    }
 }
@@ -1,56 +1,187 @@
 #![doc=include_str!("../README.md")]
 
+use std::marker::PhantomData;
+
 use bungee_sys::BungeeStretcher;
 
-pub use crate::{InputChunk, OutputChunk, Request};
+use crate::{
+    edition::{Basic, EditionMarker},
+    realtime, Edition,
+};
+pub use crate::{Error, InputChunk, MuteRange, OutputChunk, Request};
 
 // -------------------------------------------------------------------------------------------------
 
-/// A safe wrapper around the Bungee stretcher instance.
-pub struct Stretcher {
-    inner: *mut BungeeStretcher,
-    sample_rate: usize,
+/// Builder for a [`Stretcher`], exposing all options of the underlying Bungee stretcher.
+///
+/// ```rust, no_run
+/// use bungee_rs::StretcherBuilder;
+///
+/// // Resample from 44.1kHz to 48kHz in the stretching pass, using a smaller synthesis hop.
+/// let stretcher = StretcherBuilder::new(44100, 2)
+///     .output_sample_rate(48000)
+///     .log2_synthesis_hop_adjust(-1)
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct StretcherBuilder<E: EditionMarker = Basic> {
+    input_sample_rate: usize,
+    output_sample_rate: usize,
     num_channels: usize,
+    log2_synthesis_hop_adjust: i32,
+    instrumentation: bool,
+    edition: PhantomData<E>,
 }
 
-unsafe impl Send for Stretcher {}
-unsafe impl Sync for Stretcher {}
+impl StretcherBuilder {
+    /// Valid range for [`Self::log2_synthesis_hop_adjust`].
+    pub const LOG2_SYNTHESIS_HOP_ADJUST_RANGE: std::ops::RangeInclusive<i32> = -1..=1;
 
-impl Stretcher {
-    /// Creates and initializes a Bungee stretcher instance.
-    ///
-    /// # Returns
-    /// A `Stretcher` instance or an error if the stretcher cannot be created.
+    /// Creates a new builder for a Basic edition stretcher with equal input and output sample
+    /// rates, the default synthesis hop and instrumentation disabled.
+    pub fn new(sample_rate: usize, num_channels: usize) -> Self {
+        Self {
+            input_sample_rate: sample_rate,
+            output_sample_rate: sample_rate,
+            num_channels,
+            log2_synthesis_hop_adjust: 0,
+            instrumentation: false,
+            edition: PhantomData,
+        }
+    }
+}
+
+impl<E: EditionMarker> StretcherBuilder<E> {
+    /// Selects the Bungee edition of the stretcher. See [`crate::edition`].
+    pub fn edition<F: EditionMarker>(self) -> StretcherBuilder<F> {
+        StretcherBuilder {
+            input_sample_rate: self.input_sample_rate,
+            output_sample_rate: self.output_sample_rate,
+            num_channels: self.num_channels,
+            log2_synthesis_hop_adjust: self.log2_synthesis_hop_adjust,
+            instrumentation: self.instrumentation,
+            edition: PhantomData,
+        }
+    }
+
+    /// Sets the sample rate of the input audio.
+    pub fn input_sample_rate(mut self, sample_rate: usize) -> Self {
+        self.input_sample_rate = sample_rate;
+        self
+    }
+
+    /// Sets the sample rate of the output audio. When it differs from the input sample rate,
+    /// the stretcher resamples the audio in the same pass.
+    pub fn output_sample_rate(mut self, sample_rate: usize) -> Self {
+        self.output_sample_rate = sample_rate;
+        self
+    }
+
+    /// Adjusts the synthesis hop size as a power of two: -1 halves the hop for lower latency
+    /// and better time resolution, 1 doubles it for better frequency resolution, 0 is the
+    /// recommended default.
+    pub fn log2_synthesis_hop_adjust(mut self, log2_hop_adjust: i32) -> Self {
+        self.log2_synthesis_hop_adjust = log2_hop_adjust;
+        self
+    }
+
+    /// Enables verbose diagnostics and checks in the Bungee library.
+    pub fn instrumentation(mut self, enable: bool) -> Self {
+        self.instrumentation = enable;
+        self
+    }
+
+    /// Creates and initializes the stretcher.
     ///
     /// # Errors
-    /// Returns an error if the sample rate or channel count is invalid, or if the
-    /// Bungee function table cannot be retrieved or the stretcher cannot be created.
-    pub fn new(sample_rate: usize, num_channels: usize) -> Result<Self, &'static str> {
-        if sample_rate == 0 {
-            return Err("Invalid sample rate");
+    /// Returns an error if a sample rate, the channel count or the hop adjustment is invalid,
+    /// or if the stretcher cannot be created.
+    pub fn build(self) -> Result<Stretcher<E>, Error> {
+        if self.input_sample_rate == 0 {
+            return Err(Error::InvalidSampleRate(self.input_sample_rate));
         }
-        if num_channels == 0 {
-            return Err("Invalid channel count");
+        if self.output_sample_rate == 0 {
+            return Err(Error::InvalidSampleRate(self.output_sample_rate));
+        }
+        if self.num_channels == 0 {
+            return Err(Error::InvalidChannelCount(self.num_channels));
+        }
+        if !StretcherBuilder::LOG2_SYNTHESIS_HOP_ADJUST_RANGE
+            .contains(&self.log2_synthesis_hop_adjust)
+        {
+            return Err(Error::InvalidSynthesisHopAdjust(
+                self.log2_synthesis_hop_adjust,
+            ));
         }
         let sample_rates = bungee_sys::SampleRates {
-            input: sample_rate as i32,
-            output: sample_rate as i32,
+            input: self.input_sample_rate as i32,
+            output: self.output_sample_rate as i32,
         };
 
-        // The C++ API defaults log2SynthesisHopAdjust to 0
-        let log2_hop_adjust = 0;
-
-        let inner =
-            bungee_sys::stretcher::create(sample_rates, num_channels as i32, log2_hop_adjust);
+        let inner = (E::FFI.create)(
+            sample_rates,
+            self.num_channels as i32,
+            self.log2_synthesis_hop_adjust,
+        );
         if inner.is_null() {
-            return Err("Failed to create Bungee stretcher");
+            return Err(Error::CreationFailed("stretcher"));
         }
 
-        Ok(Stretcher {
+        let mut stretcher = Stretcher {
             inner,
-            sample_rate,
-            num_channels,
-        })
+            input_sample_rate: self.input_sample_rate,
+            output_sample_rate: self.output_sample_rate,
+            num_channels: self.num_channels,
+            log2_synthesis_hop_adjust: self.log2_synthesis_hop_adjust,
+            input_chunk: InputChunk { begin: 0, end: 0 },
+            edition: PhantomData,
+        };
+        if self.instrumentation {
+            stretcher.enable_instrumentation(true);
+        }
+        Ok(stretcher)
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+/// A safe wrapper around the Bungee stretcher instance.
+///
+/// The stretcher uses the Bungee Basic edition by default: see [`crate::edition`] and
+/// [`StretcherBuilder::edition`] for other editions.
+pub struct Stretcher<E: EditionMarker = Basic> {
+    inner: *mut BungeeStretcher,
+    input_sample_rate: usize,
+    output_sample_rate: usize,
+    num_channels: usize,
+    log2_synthesis_hop_adjust: i32,
+    input_chunk: InputChunk,
+    edition: PhantomData<E>,
+}
+
+unsafe impl<E: EditionMarker> Send for Stretcher<E> {}
+unsafe impl<E: EditionMarker> Sync for Stretcher<E> {}
+
+impl Stretcher {
+    /// Creates and initializes a Bungee stretcher instance with equal input and output
+    /// sample rates and default options. Use [`StretcherBuilder`] to configure all options.
+    ///
+    /// # Returns
+    /// A `Stretcher` instance or an error if the stretcher cannot be created.
+    ///
+    /// # Errors
+    /// Returns an error if the sample rate or channel count is invalid, or if the
+    /// Bungee function table cannot be retrieved or the stretcher cannot be created.
+    pub fn new(sample_rate: usize, num_channels: usize) -> Result<Self, Error> {
+        StretcherBuilder::new(sample_rate, num_channels).build()
+    }
+}
+
+impl<E: EditionMarker> Stretcher<E> {
+    /// Returns the stretcher's Bungee edition.
+    pub fn edition(&self) -> Edition {
+        E::EDITION
     }
 
     /// Returns the stretcher's C++ handle.  
@@ -58,9 +189,19 @@ impl Stretcher {
         self.inner
     }
 
-    /// Returns the stretcher's sample rate.  
+    /// Returns the stretcher's input sample rate.  
     pub fn sample_rate(&self) -> usize {
-        self.sample_rate
+        self.input_sample_rate
+    }
+
+    /// Returns the stretcher's input sample rate.  
+    pub fn input_sample_rate(&self) -> usize {
+        self.input_sample_rate
+    }
+
+    /// Returns the stretcher's output sample rate.  
+    pub fn output_sample_rate(&self) -> usize {
+        self.output_sample_rate
     }
 
     /// Returns the stretcher's channel layout.  
@@ -68,70 +209,211 @@ impl Stretcher {
         self.num_channels
     }
 
+    /// Returns the stretcher's synthesis hop adjustment.  
+    pub fn log2_synthesis_hop_adjust(&self) -> i32 {
+        self.log2_synthesis_hop_adjust
+    }
+
+    /// Enables or disables verbose diagnostics and checks in the Bungee library.
+    pub fn enable_instrumentation(&mut self, enable: bool) {
+        (E::FFI.enable_instrumentation)(self.inner, enable as i32);
+    }
+
     /// Returns the largest number of frames that might be requested by specify_grain().
     /// This helps the caller to allocate large enough buffers because it is guaranteed that
     /// `InputChunk.len()` will not exceed this number.
     pub fn max_input_frame_count(&self) -> usize {
-        bungee_sys::stretcher::max_input_frame_count(self.inner) as usize
+        (E::FFI.max_input_frame_count)(self.inner) as usize
     }
 
     /// Adjusts `request.position` for a run-in.
     pub fn preroll(&mut self, request: &mut Request) {
-        let mut ffi_request: bungee_sys::Request = (*request).into();
-        bungee_sys::stretcher::preroll(self.inner, &mut ffi_request);
-        *request = ffi_request.into();
+        realtime::guard(|| {
+            let mut ffi_request: bungee_sys::Request = (*request).into();
+            (E::FFI.preroll)(self.inner, &mut ffi_request);
+            *request = ffi_request.into();
+        })
     }
 
     /// Specifies a grain and computes the necessary input audio segment.
+    ///
+    /// # Panics
+    /// Panics if the request is invalid. See [`Self::try_specify_grain`].
     pub fn specify_grain(&mut self, request: &Request) -> InputChunk {
-        let ffi_request: bungee_sys::Request = (*request).into();
+        self.try_specify_grain(request)
+            .unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Specifies a grain and computes the necessary input audio segment.
+    ///
+    /// # Errors
+    /// Returns an error if the request's pitch is not positive and finite, its speed is not
+    /// finite or its position is infinite.
+    pub fn try_specify_grain(&mut self, request: &Request) -> Result<InputChunk, Error> {
         // The C++ API defaults bufferStartPosition to 0.0, so we do the same.
-        let buffer_start_pos = 0.0;
-        bungee_sys::stretcher::specify_grain(self.inner, &ffi_request, buffer_start_pos).into()
+        self.try_specify_grain_in_buffer(request, 0.0)
     }
 
-    /// Begins processing the grain with the provided audio data.
+    /// Specifies a grain and computes the necessary input audio segment relative to an input
+    /// buffer whose first frame is at `buffer_start_position` in input frames.
+    ///
+    /// This allows feeding grains from a sliding window of input audio, such as a
+    /// [`GrainInputBuffer`](crate::input_buffer::GrainInputBuffer): the returned chunk's `begin`
+    /// and `end` are frame offsets relative to the buffer's start instead of the start of
+    /// the audio track.
     ///
-    /// # Safety
-    /// `data` must be a valid pointer to audio data corresponding to the chunk specified
-    /// by a prior call to `specify_grain`.
+    /// # Panics
+    /// Panics if the request or buffer start position is invalid.
+    /// See [`Self::try_specify_grain_in_buffer`].
+    pub fn specify_grain_in_buffer(
+        &mut self,
+        request: &Request,
+        buffer_start_position: f64,
+    ) -> InputChunk {
+        self.try_specify_grain_in_buffer(request, buffer_start_position)
+            .unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Specifies a grain and computes the necessary input audio segment relative to an input
+    /// buffer whose first frame is at `buffer_start_position` in input frames.
+    ///
+    /// # Errors
+    /// Returns an error if the request is invalid (see [`Self::try_specify_grain`]), or if the
+    /// buffer start position is not finite.
+    pub fn try_specify_grain_in_buffer(
+        &mut self,
+        request: &Request,
+        buffer_start_position: f64,
+    ) -> Result<InputChunk, Error> {
+        realtime::guard(|| {
+            request.validate()?;
+            if !buffer_start_position.is_finite() {
+                return Err(Error::NonFinitePosition(buffer_start_position));
+            }
+            let ffi_request: bungee_sys::Request = (*request).into();
+            self.input_chunk =
+                (E::FFI.specify_grain)(self.inner, &ffi_request, buffer_start_position).into();
+            Ok(self.input_chunk)
+        })
+    }
+
+    /// Begins processing the grain with the provided audio data, which must hold the input
+    /// chunk specified by the prior call to `specify_grain`. The nth channel's frames start
+    /// at `data[n * channel_stride]`.
+    ///
+    /// # Panics
+    /// Panics if `data` is too small. See [`Self::try_analyse_grain_with_mute`].
     pub fn analyse_grain(&mut self, data: &mut [f32], channel_stride: usize) {
         // The C++ API defaults mute counts to 0, so we do the same.
-        let mute_head = 0;
-        let mute_tail = 0;
-        bungee_sys::stretcher::analyse_grain(
-            self.inner,
-            data.as_ptr(),
-            channel_stride as isize,
-            mute_head,
-            mute_tail,
-        );
+        self.analyse_grain_with_mute(data, channel_stride, MuteRange::default());
     }
 
-    /// Completes processing of the grain and writes the output.
-    pub fn synthesise_grain(&mut self, output: &mut OutputChunk) {
-        let mut ffi_output: bungee_sys::OutputChunk = output.into();
-        bungee_sys::stretcher::synthesise_grain(self.inner, &mut ffi_output);
-        *output = ffi_output.into();
+    /// Begins processing the grain with the provided audio data, muting the given number of
+    /// frames at the start and end of the input chunk. Muted frames are ignored, so callers
+    /// don't need to zero-fill regions which lie outside of their input audio.
+    /// See [`InputChunk::mute_range`].
+    ///
+    /// # Panics
+    /// Panics if `data` is too small or the mute range is invalid.
+    /// See [`Self::try_analyse_grain_with_mute`].
+    pub fn analyse_grain_with_mute(
+        &mut self,
+        data: &[f32],
+        channel_stride: usize,
+        mute: MuteRange,
+    ) {
+        self.try_analyse_grain_with_mute(data, channel_stride, mute)
+            .unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Begins processing the grain with the provided audio data, muting the given number of
+    /// frames at the start and end of the input chunk.
+    ///
+    /// # Errors
+    /// Returns an error if the muted frames exceed the input chunk's length, or if `data`
+    /// can't hold `InputChunk::len()` frames for each channel at the given channel stride.
+    pub fn try_analyse_grain_with_mute(
+        &mut self,
+        data: &[f32],
+        channel_stride: usize,
+        mute: MuteRange,
+    ) -> Result<(), Error> {
+        realtime::guard(|| {
+            let len = self.input_chunk.len();
+            let muted = mute.head.checked_add(mute.tail);
+            if !matches!(muted, Some(muted) if muted <= len) {
+                return Err(Error::InvalidMuteRange {
+                    head: mute.head,
+                    tail: mute.tail,
+                    len,
+                });
+            }
+            if self.num_channels > 1 && channel_stride < len {
+                return Err(Error::BufferTooSmall {
+                    channel: 0,
+                    len: channel_stride,
+                    required: len,
+                });
+            }
+            for channel in 0..self.num_channels {
+                let available = data.len().saturating_sub(channel * channel_stride);
+                if available < len {
+                    return Err(Error::BufferTooSmall {
+                        channel,
+                        len: available,
+                        required: len,
+                    });
+                }
+            }
+            (E::FFI.analyse_grain)(
+                self.inner,
+                data.as_ptr(),
+                channel_stride as isize,
+                mute.head as i32,
+                mute.tail as i32,
+            );
+            Ok(())
+        })
+    }
+
+    /// Completes processing of the grain and returns a view of its output.
+    ///
+    /// The output data is owned by the stretcher and remains valid until the stretcher is
+    /// used again.
+    pub fn synthesise_grain(&mut self) -> OutputChunk<'_> {
+        realtime::guard(|| {
+            let mut ffi_output = bungee_sys::OutputChunk {
+                data: std::ptr::null_mut(),
+                frame_count: 0,
+                channel_stride: 0,
+                request: [std::ptr::null(), std::ptr::null()],
+            };
+            (E::FFI.synthesise_grain)(self.inner, &mut ffi_output);
+            // Safety: Bungee fills in pointers to its internal output buffer and requests, which
+            // stay valid until the next call into the stretcher, which requires `&mut self`.
+            unsafe { OutputChunk::from_ffi(&ffi_output, self.num_channels) }
+        })
     }
 
     /// Prepares `request.position` and `request.reset` for the subsequent grain.
     pub fn next(&mut self, request: &mut Request) {
-        let mut ffi_request = (*request).into();
-        bungee_sys::stretcher::next(self.inner, &mut ffi_request);
-        *request = ffi_request.into();
+        realtime::guard(|| {
+            let mut ffi_request = (*request).into();
+            (E::FFI.next)(self.inner, &mut ffi_request);
+            *request = ffi_request.into();
+        })
     }
 
     /// Returns true (non-zero) if the stretcher's pipeline is flushed.
     pub fn is_flushed(&self) -> bool {
-        bungee_sys::stretcher::is_flushed(self.inner) != 0
+        realtime::guard(|| (E::FFI.is_flushed)(self.inner) != 0)
     }
 }
 
-impl Drop for Stretcher {
+impl<E: EditionMarker> Drop for Stretcher<E> {
     /// Destroys a Bungee stretcher instance and frees its memory.
     fn drop(&mut self) {
-        bungee_sys::stretcher::destroy(self.inner);
+        (E::FFI.destroy)(self.inner);
     }
 }
 
@@ -170,15 +452,194 @@ mod tests {
         stretcher.analyse_grain(&mut data, 1);
 
         // Synthesise grain
-        let mut output_data = vec![0.0f32; 1024]; // Allocate buffer for output
-        let mut output = OutputChunk::new(&mut output_data, 1);
-        stretcher.synthesise_grain(&mut output);
+        let output = stretcher.synthesise_grain();
+        let output_frame_count = output.frame_count;
 
         // Next
         stretcher.next(&mut request);
 
         // Verify we didn't panic and the request was updated
         assert!(request.position >= 0.0);
-        assert!(output.frame_count > 0);
+        assert!(output_frame_count > 0);
+    }
+
+    #[test]
+    fn stretcher_builder() {
+        let stretcher = StretcherBuilder::new(44100, 2)
+            .output_sample_rate(48000)
+            .log2_synthesis_hop_adjust(-1)
+            .instrumentation(true)
+            .build()
+            .unwrap();
+        assert_eq!(stretcher.input_sample_rate(), 44100);
+        assert_eq!(stretcher.output_sample_rate(), 48000);
+        assert_eq!(stretcher.num_channels(), 2);
+        assert_eq!(stretcher.log2_synthesis_hop_adjust(), -1);
+        assert_eq!(stretcher.edition(), Edition::Basic);
+
+        #[cfg(feature = "pro")]
+        {
+            let stretcher = StretcherBuilder::new(44100, 2)
+                .edition::<crate::edition::Pro>()
+                .build()
+                .unwrap();
+            assert_eq!(stretcher.edition(), Edition::Pro);
+        }
+
+        assert!(matches!(
+            StretcherBuilder::new(44100, 2)
+                .output_sample_rate(0)
+                .build(),
+            Err(Error::InvalidSampleRate(0))
+        ));
+        assert!(matches!(
+            StretcherBuilder::new(44100, 0).build(),
+            Err(Error::InvalidChannelCount(0))
+        ));
+        assert!(matches!(
+            StretcherBuilder::new(44100, 2)
+                .log2_synthesis_hop_adjust(8)
+                .build(),
+            Err(Error::InvalidSynthesisHopAdjust(8))
+        ));
+    }
+
+    #[cfg(feature = "pro")]
+    #[test]
+    fn stretcher_editions_side_by_side() {
+        use crate::edition::Pro;
+        use std::ffi::CStr;
+
+        // the Basic and Pro bindings are linked into one binary and report their own edition
+        let edition = |ptr| unsafe { CStr::from_ptr(ptr) }.to_str().unwrap();
+        assert_eq!(edition(bungee_sys::stretcher::edition()), "Basic");
+        assert_eq!(edition(bungee_sys::pro::stretcher::edition()), "Pro");
+
+        // and both editions process interleaved in one process
+        let mut basic = Stretcher::new(44100, 1).unwrap();
+        let mut pro = StretcherBuilder::new(44100, 1)
+            .edition::<Pro>()
+            .build()
+            .unwrap();
+        let request = Request {
+            position: 0.0,
+            speed: 1.0,
+            pitch: 1.0,
+            reset: true,
+        };
+        let (mut basic_request, mut pro_request) = (request, request);
+        basic.preroll(&mut basic_request);
+        pro.preroll(&mut pro_request);
+        for _ in 0..8 {
+            let chunk = basic.specify_grain(&basic_request);
+            let mut data = vec![0.5f32; (chunk.end - chunk.begin) as usize];
+            basic.analyse_grain(&mut data, 1);
+            assert!(basic.synthesise_grain().frame_count > 0);
+            basic.next(&mut basic_request);
+
+            let chunk = pro.specify_grain(&pro_request);
+            let mut data = vec![0.5f32; (chunk.end - chunk.begin) as usize];
+            pro.analyse_grain(&mut data, 1);
+            assert!(pro.synthesise_grain().frame_count > 0);
+            pro.next(&mut pro_request);
+        }
+        assert_eq!(basic_request.position, pro_request.position);
+    }
+
+    #[test]
+    fn stretcher_invalid_requests() {
+        let mut stretcher = Stretcher::new(44100, 1).unwrap();
+
+        let request = Request {
+            pitch: 1.0,
+            speed: 1.0,
+            position: 0.0,
+            reset: true,
+        };
+        assert!(matches!(
+            stretcher.try_specify_grain(&Request {
+                pitch: 0.0,
+                ..request
+            }),
+            Err(Error::InvalidPitch(_))
+        ));
+        assert!(matches!(
+            stretcher.try_specify_grain(&Request {
+                speed: f64::INFINITY,
+                ..request
+            }),
+            Err(Error::InvalidSpeed(_))
+        ));
+        assert!(matches!(
+            stretcher.try_specify_grain(&Request {
+                position: f64::NEG_INFINITY,
+                ..request
+            }),
+            Err(Error::NonFinitePosition(_))
+        ));
+        // NaN positions are valid flush grains
+        assert!(stretcher
+            .try_specify_grain(&Request {
+                position: f64::NAN,
+                ..request
+            })
+            .is_ok());
+    }
+
+    #[test]
+    fn stretcher_analyse_with_mute() {
+        let mut stretcher = Stretcher::new(44100, 2).unwrap();
+        let mut request = Request {
+            pitch: 1.0,
+            speed: 1.0,
+            position: 0.0,
+            reset: true,
+        };
+        stretcher.preroll(&mut request);
+
+        // the pre-roll grain starts before the source, and the source ends within the grain
+        let chunk = stretcher.specify_grain(&request);
+        assert!(chunk.begin < 0);
+        let source_len = (chunk.end - 10) as usize;
+        let mute = chunk.mute_range(source_len);
+        assert_eq!(mute.head, (-chunk.begin) as usize);
+        assert_eq!(mute.tail, 10);
+        let empty = chunk.mute_range(0);
+        assert_eq!(empty.head + empty.tail, chunk.len());
+
+        let len = chunk.len();
+        let data = vec![0.0f32; len * 2];
+        assert_eq!(
+            stretcher.try_analyse_grain_with_mute(&data[1..], len, mute),
+            Err(Error::BufferTooSmall {
+                channel: 1,
+                len: len - 1,
+                required: len
+            })
+        );
+        assert_eq!(
+            stretcher.try_analyse_grain_with_mute(&data, len - 1, mute),
+            Err(Error::BufferTooSmall {
+                channel: 0,
+                len: len - 1,
+                required: len
+            })
+        );
+        assert!(matches!(
+            stretcher.try_analyse_grain_with_mute(&data, len, MuteRange { head: len, tail: 1 }),
+            Err(Error::InvalidMuteRange { .. })
+        ));
+        let overflowing = MuteRange {
+            head: usize::MAX,
+            tail: 2,
+        };
+        assert!(matches!(
+            stretcher.try_analyse_grain_with_mute(&data, len, overflowing),
+            Err(Error::InvalidMuteRange { .. })
+        ));
+        assert!(stretcher
+            .try_analyse_grain_with_mute(&data, len, mute)
+            .is_ok());
+        assert!(stretcher.synthesise_grain().frame_count > 0);
     }
 }
@@ -0,0 +1,142 @@
+//! Helpers to match tempos and durations: conversions between BPM, durations, speeds, frame
+//! counts and pitch in semitones.
+//!
+//! Speeds are defined like [`Request::speed`](crate::Request::speed): output frames =
+//! input frames / speed. Pitches are frequency multipliers like
+//! [`Request::pitch`](crate::Request::pitch), which Bungee applies independently of the speed,
+//! so a transposition stays in key no matter how the tempo changes (key-lock).
+
+use std::time::Duration;
+
+use crate::Error;
+
+// -------------------------------------------------------------------------------------------------
+
+/// Returns the speed which changes the tempo of audio from `source_bpm` to `target_bpm`.
+///
+/// # Errors
+/// Returns an error if a tempo is not a positive, finite number.
+pub fn speed_for_bpm(source_bpm: f64, target_bpm: f64) -> Result<f64, Error> {
+    validate_bpm(source_bpm)?;
+    validate_bpm(target_bpm)?;
+    Ok(target_bpm / source_bpm)
+}
+
+/// Returns the speed which stretches `source_len` input frames to `target_len` output frames.
+///
+/// # Errors
+/// Returns an error if either length is zero.
+pub fn speed_for_length(source_len: usize, target_len: usize) -> Result<f64, Error> {
+    if source_len == 0 {
+        return Err(Error::InvalidInputFrameCount(source_len));
+    }
+    if target_len == 0 {
+        return Err(Error::InvalidOutputFrameCount(target_len as f64));
+    }
+    Ok(source_len as f64 / target_len as f64)
+}
+
+/// Returns the number of output frames that `input_len` input frames get stretched to at the
+/// given speed, as rendered by [`offline::stretch`](crate::offline::stretch).
+pub fn output_len(input_len: usize, speed: f64) -> usize {
+    (input_len as f64 / speed).round() as usize
+}
+
+/// Returns the number of output frames which audio at `source_bpm` takes at `target_bpm`.
+///
+/// # Errors
+/// Returns an error if a tempo is not a positive, finite number.
+pub fn output_len_for_bpm(
+    input_len: usize,
+    source_bpm: f64,
+    target_bpm: f64,
+) -> Result<usize, Error> {
+    validate_bpm(source_bpm)?;
+    validate_bpm(target_bpm)?;
+    Ok((input_len as f64 * source_bpm / target_bpm).round() as usize)
+}
+
+/// Returns the number of frames, rounded to the nearest frame, of a duration at the given
+/// sample rate.
+pub fn frames_for_duration(duration: Duration, sample_rate: usize) -> usize {
+    (duration.as_secs_f64() * sample_rate as f64).round() as usize
+}
+
+/// Returns the duration of a number of frames at the given sample rate.
+pub fn duration_for_frames(frame_count: usize, sample_rate: usize) -> Duration {
+    if sample_rate == 0 {
+        return Duration::ZERO;
+    }
+    Duration::from_secs_f64(frame_count as f64 / sample_rate as f64)
+}
+
+// -------------------------------------------------------------------------------------------------
+
+/// Returns the pitch, as frequency multiplier, of a transposition in semitones. Fractional
+/// semitones express cents: 0.5 semitones are 50 cents.
+pub fn pitch_for_semitones(semitones: f64) -> f64 {
+    2f64.powf(semitones / 12.0)
+}
+
+/// Returns the transposition in semitones of a pitch, as frequency multiplier.
+pub fn semitones_for_pitch(pitch: f64) -> f64 {
+    12.0 * pitch.log2()
+}
+
+/// Returns the pitch of a transposition in semitones at the given speed. With `key_lock`, the
+/// pitch only depends on the transposition. Without, the pitch also follows the speed, like a
+/// turntable or tape machine does.
+pub fn pitch_for_speed(speed: f64, semitones: f64, key_lock: bool) -> f64 {
+    let pitch = pitch_for_semitones(semitones);
+    if key_lock {
+        pitch
+    } else {
+        pitch * speed
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+/// Verifies that a tempo is a positive, finite number.
+pub(crate) fn validate_bpm(bpm: f64) -> Result<(), Error> {
+    if !(bpm.is_finite() && bpm > 0.0) {
+        return Err(Error::InvalidTempo(bpm));
+    }
+    Ok(())
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tempo_conversions() {
+        assert_eq!(speed_for_bpm(120.0, 150.0).unwrap(), 1.25);
+        assert!(matches!(
+            speed_for_bpm(0.0, 120.0),
+            Err(Error::InvalidTempo(_))
+        ));
+        assert_eq!(speed_for_length(1000, 2000).unwrap(), 0.5);
+        assert!(speed_for_length(0, 1000).is_err());
+        assert!(speed_for_length(1000, 0).is_err());
+
+        assert_eq!(output_len(1000, 0.75), 1333);
+        assert_eq!(output_len_for_bpm(44100, 120.0, 128.0).unwrap(), 41344);
+        assert_eq!(
+            frames_for_duration(Duration::from_millis(1500), 44100),
+            66150
+        );
+        assert_eq!(
+            duration_for_frames(66150, 44100),
+            Duration::from_millis(1500)
+        );
+
+        assert_eq!(pitch_for_semitones(12.0), 2.0);
+        assert_eq!(pitch_for_semitones(-24.0), 0.25);
+        assert!((semitones_for_pitch(pitch_for_semitones(0.5)) - 0.5).abs() < 1e-12);
+        assert_eq!(pitch_for_speed(1.5, 12.0, true), 2.0);
+        assert_eq!(pitch_for_speed(1.5, 12.0, false), 3.0);
+    }
+}
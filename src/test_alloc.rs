@@ -0,0 +1,64 @@
+//! A counting global allocator for the unit tests, which verifies that real-time code paths
+//! don't allocate. Allocations of the Bungee C++ library bypass Rust's global allocator, so
+//! they are not counted.
+
+use std::{
+    alloc::{GlobalAlloc, Layout},
+    cell::Cell,
+};
+
+// -------------------------------------------------------------------------------------------------
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Counts allocations, reallocations and deallocations of the calling thread.
+struct CountingAllocator;
+
+impl CountingAllocator {
+    fn count() {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+    }
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        Self::count();
+        Inner.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        Self::count();
+        Inner.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        Self::count();
+        Inner.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        Self::count();
+        Inner.dealloc(ptr, layout)
+    }
+}
+
+#[cfg(not(feature = "debug_assert_no_alloc"))]
+use std::alloc::System as Inner;
+// abort when allocating within real-time entry points
+#[cfg(feature = "debug_assert_no_alloc")]
+use assert_no_alloc::AllocDisabler as Inner;
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+// -------------------------------------------------------------------------------------------------
+
+/// Runs `f` and returns its result along with the number of heap operations it performed on
+/// the calling thread.
+pub fn count_allocations<R>(f: impl FnOnce() -> R) -> (R, usize) {
+    let start = ALLOCATIONS.with(Cell::get);
+    let result = f();
+    (result, ALLOCATIONS.with(Cell::get) - start)
+}
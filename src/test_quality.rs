@@ -0,0 +1,256 @@
+//! Quality regression tests: stretch synthetic signals with the [`Stream`] and the raw
+//! [`Stretcher`] grain loop, and verify frequencies, lengths, levels and sample values of the
+//! output.
+//!
+//! Every test checks both drivers; run them with `cargo test --lib test_quality`. Signals and
+//! analysis helpers live in [`crate::test_signals`].
+
+use crate::test_signals::*;
+use crate::{Request, StreamBuilder, Stretcher};
+
+// -------------------------------------------------------------------------------------------------
+
+/// Frames at the start and end of the output which are ignored by level measurements.
+const EDGE: usize = 4096;
+
+// -------------------------------------------------------------------------------------------------
+
+/// Stretches mono input with a latency compensated stream in blocks of 1024 frames.
+fn stretch_stream(input: &[f32], speed: f64, pitch: f64) -> Vec<f32> {
+    const BLOCK_SIZE: usize = 1024;
+    let mut stream = StreamBuilder::new(SAMPLE_RATE, 1, BLOCK_SIZE)
+        .latency_compensation(true)
+        .build()
+        .unwrap();
+    let mut buffer = vec![vec![
+        0.0f32;
+        (BLOCK_SIZE as f64 / speed).ceil() as usize + 1
+    ]];
+    let mut output = Vec::new();
+    for block in input.chunks(BLOCK_SIZE) {
+        let frame_count = stream.process_planar(
+            Some(&[block][..]),
+            &mut buffer,
+            block.len(),
+            block.len() as f64 / speed,
+            pitch,
+        );
+        output.extend_from_slice(&buffer[0][..frame_count]);
+    }
+    let mut tail = vec![vec![0.0f32; stream.finish_frame_count()]];
+    let frame_count = stream.finish(&mut tail);
+    output.extend_from_slice(&tail[0][..frame_count]);
+    output
+}
+
+/// Stretches mono input with the raw stretcher grain loop, keeping the output frames which
+/// map to input positions within the input.
+fn stretch_grains(input: &[f32], speed: f64, pitch: f64) -> Vec<f32> {
+    let mut stretcher = Stretcher::new(SAMPLE_RATE, 1).unwrap();
+    let mut data = vec![0.0f32; stretcher.max_input_frame_count()];
+    let mut request = Request {
+        position: 0.0,
+        speed,
+        pitch,
+        reset: true,
+    };
+    stretcher.preroll(&mut request);
+
+    let mut output = Vec::new();
+    loop {
+        let chunk = stretcher.specify_grain(&request);
+        let mute = chunk.mute_range(input.len());
+        let begin = (chunk.begin + mute.head as isize) as usize;
+        let end = (chunk.end - mute.tail as isize) as usize;
+        if end > begin {
+            data[mute.head..mute.head + end - begin].copy_from_slice(&input[begin..end]);
+        }
+        stretcher.analyse_grain_with_mute(&data, data.len(), mute);
+
+        let grain = stretcher.synthesise_grain();
+        if let [Some(first), Some(last)] = grain.request {
+            let step = (last.position - first.position) / grain.frame_count as f64;
+            for (frame, sample) in grain.channel(0).iter().enumerate() {
+                let position = first.position + step * frame as f64;
+                if position >= input.len() as f64 {
+                    return output;
+                }
+                if position >= 0.0 {
+                    output.push(*sample);
+                }
+            }
+        }
+        stretcher.next(&mut request);
+    }
+}
+
+/// Stretches mono input with the given speed and pitch.
+type Stretch = fn(&[f32], f64, f64) -> Vec<f32>;
+
+/// Both stretch drivers, with their names for assertion messages.
+const DRIVERS: [(&str, Stretch); 2] = [("stream", stretch_stream), ("stretcher", stretch_grains)];
+
+// -------------------------------------------------------------------------------------------------
+
+#[test]
+fn quality_output_length() {
+    let input = sine(440.0, 0.5, SAMPLE_RATE + 123);
+    for speed in [0.5, 0.7, 1.0, 1.3, 2.0] {
+        let expected = (input.len() as f64 / speed).round() as usize;
+        let output = stretch_stream(&input, speed, 1.0);
+        assert_eq!(output.len(), expected, "stream at speed {speed}");
+        let output = stretch_grains(&input, speed, 1.0);
+        assert!(
+            output.len().abs_diff(expected) <= 2,
+            "stretcher at speed {speed}: {} != {expected}",
+            output.len()
+        );
+    }
+}
+
+#[test]
+fn quality_frequency_preserved_under_speed_change() {
+    for frequency in [220.0, 440.0, 1000.0, 3000.0] {
+        let input = sine(frequency, 0.5, 2 * SAMPLE_RATE);
+        for speed in [0.5, 0.8, 1.25, 2.0] {
+            for (name, stretch) in DRIVERS {
+                let output = stretch(&input, speed, 1.0);
+                assert_clean(name, &output);
+                let middle = &output[output.len() / 2 - 4096..][..8192];
+                let measured = dominant_frequency(middle, frequency);
+                assert!(
+                    (measured / frequency - 1.0).abs() < 0.01,
+                    "{name}: {frequency} Hz at speed {speed} measured {measured} Hz"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn quality_frequency_ratio_under_pitch_change() {
+    let frequency = 440.0;
+    let input = sine(frequency, 0.5, 2 * SAMPLE_RATE);
+    for pitch in [0.5, 0.75, 1.5, 2.0] {
+        for speed in [0.8, 1.0] {
+            for (name, stretch) in DRIVERS {
+                let output = stretch(&input, speed, pitch);
+                assert_clean(name, &output);
+                let middle = &output[output.len() / 2 - 4096..][..8192];
+                let expected = frequency * pitch;
+                let measured = dominant_frequency(middle, expected);
+                assert!(
+                    (measured / expected - 1.0).abs() < 0.01,
+                    "{name}: pitch {pitch} at speed {speed} measured {measured} Hz"
+                );
+                assert!(
+                    magnitude(middle, expected) > 10.0 * magnitude(middle, frequency),
+                    "{name}: pitch {pitch} at speed {speed} kept the original frequency"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn quality_chirp_follows_time_map() {
+    let (from, to, len) = (200.0, 2000.0, 2 * SAMPLE_RATE);
+    let input = chirp(from, to, len);
+    for speed in [0.5, 1.5] {
+        for (name, stretch) in DRIVERS {
+            let output = stretch(&input, speed, 1.0);
+            assert_clean(name, &output);
+            // output frame t plays input frame t * speed
+            for fraction in [0.25, 0.5, 0.75] {
+                let center = (output.len() as f64 * fraction) as usize;
+                let block = &output[center - 1024..center + 1024];
+                let expected = chirp_frequency(from, to, len, center as f64 * speed);
+                let measured = dominant_frequency(block, expected);
+                assert!(
+                    (measured / expected - 1.0).abs() < 0.03,
+                    "{name}: speed {speed} at {fraction}: {measured} Hz != {expected} Hz"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn quality_impulse_train_spacing() {
+    const PERIOD: usize = 4410;
+    let input = impulse_train(PERIOD, 2 * SAMPLE_RATE);
+    for speed in [0.5, 0.8, 1.5] {
+        for (name, stretch) in DRIVERS {
+            let output = stretch(&input, speed, 1.0);
+            assert_clean(name, &output);
+            let expected_spacing = PERIOD as f64 / speed;
+            let transients = transients(&output, (expected_spacing / 2.0) as usize);
+            assert!(
+                transients.len().abs_diff(2 * SAMPLE_RATE / PERIOD) <= 1,
+                "{name}: speed {speed}: {} transients",
+                transients.len()
+            );
+            let spacing = (transients[transients.len() - 1] - transients[0]) as f64
+                / (transients.len() - 1) as f64;
+            assert!(
+                (spacing / expected_spacing - 1.0).abs() < 0.02,
+                "{name}: speed {speed}: spacing {spacing} != {expected_spacing}"
+            );
+        }
+    }
+}
+
+#[test]
+fn quality_rms_stability() {
+    const BLOCK_SIZE: usize = 4096;
+    for (signal, input, block_tolerance) in [
+        ("sine", sine(440.0, 0.5, 2 * SAMPLE_RATE), 1.0),
+        ("noise", noise(2 * SAMPLE_RATE), 3.0),
+    ] {
+        let input_rms = rms(&input);
+        for speed in [0.5, 1.0, 1.5] {
+            for pitch in [0.8, 1.0, 1.25] {
+                for (name, stretch) in DRIVERS {
+                    let output = stretch(&input, speed, pitch);
+                    assert_clean(name, &output);
+                    let body = &output[EDGE..output.len() - EDGE];
+                    let level = decibels(rms(body) / input_rms);
+                    assert!(
+                        level.abs() < 1.5,
+                        "{name}: {signal} at speed {speed}, pitch {pitch}: {level} dB"
+                    );
+                    for block in body.chunks_exact(BLOCK_SIZE) {
+                        let level = decibels(rms(block) / input_rms);
+                        assert!(
+                            level.abs() < block_tolerance,
+                            "{name}: {signal} block at speed {speed}, pitch {pitch}: {level} dB"
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn quality_no_denormals_in_decays() {
+    // a decaying sine, followed by silence
+    let mut input = sine(440.0, 0.5, 2 * SAMPLE_RATE);
+    for (i, sample) in input.iter_mut().enumerate() {
+        let t = i as f32 / SAMPLE_RATE as f32;
+        *sample = if t < 1.0 {
+            *sample * (-t * 20.0).exp()
+        } else {
+            0.0
+        };
+    }
+    for speed in [0.5, 1.0, 2.0] {
+        for (name, stretch) in DRIVERS {
+            let output = stretch(&input, speed, 1.5);
+            assert_clean(name, &output);
+            // the silent tail stays silent
+            let tail = &output[output.len() - EDGE..];
+            assert!(rms(tail) < 1e-6, "{name}: speed {speed}: noisy tail");
+        }
+    }
+}
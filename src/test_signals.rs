@@ -0,0 +1,123 @@
+//! Synthetic test signals and analysis helpers, shared by the unit tests and the quality
+//! regression tests.
+
+// -------------------------------------------------------------------------------------------------
+
+pub(crate) const SAMPLE_RATE: usize = 44100;
+
+// -------------------------------------------------------------------------------------------------
+
+/// Sine with the given frequency in Hz and peak amplitude.
+pub(crate) fn sine(frequency: f64, amplitude: f64, len: usize) -> Vec<f32> {
+    (0..len)
+        .map(|i| {
+            let t = i as f64 / SAMPLE_RATE as f64;
+            (amplitude * (std::f64::consts::TAU * frequency * t).sin()) as f32
+        })
+        .collect()
+}
+
+/// Linear chirp from `from` to `to` Hz with a peak amplitude of 0.5.
+pub(crate) fn chirp(from: f64, to: f64, len: usize) -> Vec<f32> {
+    let duration = len as f64 / SAMPLE_RATE as f64;
+    (0..len)
+        .map(|i| {
+            let t = i as f64 / SAMPLE_RATE as f64;
+            let phase = from * t + (to - from) * t * t / (2.0 * duration);
+            (0.5 * (std::f64::consts::TAU * phase).sin()) as f32
+        })
+        .collect()
+}
+
+/// Frequency of a linear chirp from `from` to `to` Hz at the given frame.
+pub(crate) fn chirp_frequency(from: f64, to: f64, len: usize, frame: f64) -> f64 {
+    from + (to - from) * frame / len as f64
+}
+
+/// Single sample impulses every `period` frames, starting at `period / 2`.
+pub(crate) fn impulse_train(period: usize, len: usize) -> Vec<f32> {
+    (0..len)
+        .map(|i| if i % period == period / 2 { 1.0 } else { 0.0 })
+        .collect()
+}
+
+/// Uniform white noise in range `[-0.5, 0.5)` (xorshift32).
+pub(crate) fn noise(len: usize) -> Vec<f32> {
+    let mut seed = 0x1234_5678u32;
+    (0..len)
+        .map(|_| {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            (seed as f64 / (u32::MAX as f64 + 1.0) - 0.5) as f32
+        })
+        .collect()
+}
+
+// -------------------------------------------------------------------------------------------------
+
+/// Magnitude of the given frequency in the Hann windowed samples (Goertzel algorithm).
+pub(crate) fn magnitude(samples: &[f32], frequency: f64) -> f64 {
+    let len = samples.len();
+    let coeff = 2.0 * (std::f64::consts::TAU * frequency / SAMPLE_RATE as f64).cos();
+    let (mut s1, mut s2) = (0.0, 0.0);
+    for (i, sample) in samples.iter().enumerate() {
+        let window = 0.5 - 0.5 * (std::f64::consts::TAU * i as f64 / len as f64).cos();
+        let s0 = *sample as f64 * window + coeff * s1 - s2;
+        s2 = s1;
+        s1 = s0;
+    }
+    (s1 * s1 + s2 * s2 - coeff * s1 * s2).sqrt() / len as f64
+}
+
+/// Average [`magnitude`] of the given frequency in blocks of `block_size` samples.
+pub(crate) fn average_magnitude(samples: &[f32], frequency: f64, block_size: usize) -> f64 {
+    let blocks = samples.chunks_exact(block_size);
+    let block_count = blocks.len();
+    blocks.map(|block| magnitude(block, frequency)).sum::<f64>() / block_count as f64
+}
+
+/// Frequency with the largest magnitude within +/- 10% of `expected`, in 0.1 Hz steps.
+pub(crate) fn dominant_frequency(samples: &[f32], expected: f64) -> f64 {
+    let steps = (0.2 * expected / 0.1) as usize;
+    (0..=steps)
+        .map(|step| 0.9 * expected + step as f64 * 0.1)
+        .max_by(|a, b| magnitude(samples, *a).total_cmp(&magnitude(samples, *b)))
+        .unwrap()
+}
+
+pub(crate) fn rms(samples: &[f32]) -> f64 {
+    (samples.iter().map(|s| (*s as f64).powi(2)).sum::<f64>() / samples.len() as f64).sqrt()
+}
+
+pub(crate) fn decibels(ratio: f64) -> f64 {
+    20.0 * ratio.log10()
+}
+
+/// Positions of the transients in the output: the loudest frame of each group of frames above
+/// half of the peak level, where groups are at least `min_distance` frames apart.
+pub(crate) fn transients(samples: &[f32], min_distance: usize) -> Vec<usize> {
+    let peak = samples.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+    let mut transients: Vec<usize> = Vec::new();
+    for (frame, sample) in samples.iter().enumerate() {
+        if sample.abs() < 0.5 * peak {
+            continue;
+        }
+        match transients.last_mut() {
+            Some(last) if frame - *last < min_distance => {
+                if sample.abs() > samples[*last].abs() {
+                    *last = frame;
+                }
+            }
+            _ => transients.push(frame),
+        }
+    }
+    transients
+}
+
+/// Asserts that all samples are finite and not subnormal.
+pub(crate) fn assert_clean(name: &str, samples: &[f32]) {
+    if let Some(frame) = samples.iter().position(|s| !(s.is_normal() || *s == 0.0)) {
+        panic!("{name}: invalid sample {} at frame {frame}", samples[frame]);
+    }
+}
@@ -0,0 +1,540 @@
+//! Warping: maps input (source) frames to output (target) frames with a list of anchors, like
+//! the warp markers of a DAW, so that transients in the input land on the target's grid.
+//!
+//! Between two markers, the input gets stretched linearly. Before the first and after the last
+//! marker, the adjacent segment's speed continues. [`offline::stretch_warped`] renders a whole
+//! source with a [`WarpMap`], and [`WarpPlayer`] plays it back in real-time with seeking.
+//!
+//! [`offline::stretch_warped`]: crate::offline::stretch_warped
+
+use crate::{
+    player::{fill_silence, Playback, IDLE_REQUEST},
+    source::AudioSource,
+    Error, Request,
+};
+
+// -------------------------------------------------------------------------------------------------
+
+/// A warp anchor, which pins a source frame position to a target frame position.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WarpMarker {
+    /// Frame position in the input audio.
+    pub source: f64,
+    /// Frame position in the output audio.
+    pub target: f64,
+}
+
+// -------------------------------------------------------------------------------------------------
+
+/// A monotonic mapping between source and target frame positions, defined by warp markers.
+///
+/// Markers are sorted and strictly increasing in both their source and target positions, so
+/// the map is invertible and the input always plays forward with a positive speed. Without
+/// markers, the map is the identity. With a single marker, it shifts the input.
+///
+/// ```rust
+/// use bungee_rs::warp::WarpMap;
+///
+/// fn main() -> Result<(), bungee_rs::Error> {
+///     // the beat at input frame 20000 should land on output frame 22050
+///     let mut warp = WarpMap::new();
+///     warp.insert(0.0, 0.0)?;
+///     warp.insert(20000.0, 22050.0)?;
+///     warp.insert(40000.0, 44100.0)?;
+///
+///     assert_eq!(warp.target_for(10000.0), 11025.0);
+///     assert_eq!(warp.source_for(33075.0), 30000.0);
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WarpMap {
+    markers: Vec<WarpMarker>,
+}
+
+impl WarpMap {
+    /// Creates a new, identity warp map without markers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new warp map from the given markers, in any order.
+    ///
+    /// # Errors
+    /// Returns an error if a marker position is not finite, or if the markers are not strictly
+    /// increasing in both their source and target positions.
+    pub fn from_markers(markers: impl IntoIterator<Item = WarpMarker>) -> Result<Self, Error> {
+        let mut warp = Self::new();
+        for marker in markers {
+            warp.insert(marker.source, marker.target)?;
+        }
+        Ok(warp)
+    }
+
+    /// Returns the markers, sorted by position.
+    pub fn markers(&self) -> &[WarpMarker] {
+        &self.markers
+    }
+
+    /// Returns the number of markers.
+    pub fn len(&self) -> usize {
+        self.markers.len()
+    }
+
+    /// Returns true when the map has no markers.
+    pub fn is_empty(&self) -> bool {
+        self.markers.is_empty()
+    }
+
+    /// Inserts a new marker. Returns the marker's index.
+    ///
+    /// # Errors
+    /// Returns an error if a position is not finite, or if the marker would break the map's
+    /// monotonicity: it must lie strictly after its predecessor and strictly before its
+    /// successor in both source and target positions.
+    pub fn insert(&mut self, source: f64, target: f64) -> Result<usize, Error> {
+        let index = self.markers.partition_point(|m| m.source < source);
+        let marker = WarpMarker { source, target };
+        self.validate(marker, index.checked_sub(1), index)?;
+        self.markers.insert(index, marker);
+        Ok(index)
+    }
+
+    /// Moves the marker at the given index to a new position. The marker must stay between
+    /// its neighbours.
+    ///
+    /// # Errors
+    /// Returns an error if a position is not finite, or if the moved marker would break the
+    /// map's monotonicity.
+    ///
+    /// # Panics
+    /// Panics if `index` is out of bounds.
+    pub fn move_marker(&mut self, index: usize, source: f64, target: f64) -> Result<(), Error> {
+        assert!(index < self.markers.len(), "marker index out of bounds");
+        let marker = WarpMarker { source, target };
+        self.validate(marker, index.checked_sub(1), index + 1)?;
+        self.markers[index] = marker;
+        Ok(())
+    }
+
+    /// Removes and returns the marker at the given index.
+    ///
+    /// # Panics
+    /// Panics if `index` is out of bounds.
+    pub fn remove(&mut self, index: usize) -> WarpMarker {
+        self.markers.remove(index)
+    }
+
+    /// Removes all markers, which resets the map to the identity.
+    pub fn clear(&mut self) {
+        self.markers.clear();
+    }
+
+    /// Returns the target (output) frame position of the given source (input) position.
+    pub fn target_for(&self, source: f64) -> f64 {
+        self.interpolate(source, |m| m.source, |m| m.target)
+    }
+
+    /// Returns the source (input) frame position of the given target (output) position.
+    pub fn source_for(&self, target: f64) -> f64 {
+        self.interpolate(target, |m| m.target, |m| m.source)
+    }
+
+    /// Returns the playback speed (see Request::speed) at the given target position: the
+    /// number of source frames per target frame in the warp segment at the position.
+    pub fn speed_at(&self, target: f64) -> f64 {
+        match self.segment(target, |m| m.target) {
+            Some((a, b)) => (b.source - a.source) / (b.target - a.target),
+            None => 1.0,
+        }
+    }
+
+    /// Returns the markers of the segment that contains the given position, or the first or
+    /// last segment for positions outside of the markers. None with less than two markers.
+    fn segment(
+        &self,
+        position: f64,
+        key: impl Fn(&WarpMarker) -> f64,
+    ) -> Option<(&WarpMarker, &WarpMarker)> {
+        let len = self.markers.len();
+        if len < 2 {
+            return None;
+        }
+        let index = self
+            .markers
+            .partition_point(|m| key(m) <= position)
+            .clamp(1, len - 1);
+        Some((&self.markers[index - 1], &self.markers[index]))
+    }
+
+    /// Maps a position from one coordinate to the other, extrapolating the first and last
+    /// segment's speed outside of the markers.
+    fn interpolate(
+        &self,
+        position: f64,
+        from: impl Fn(&WarpMarker) -> f64,
+        to: impl Fn(&WarpMarker) -> f64,
+    ) -> f64 {
+        match (self.segment(position, &from), self.markers.first()) {
+            (Some((a, b)), _) => {
+                to(a) + (position - from(a)) * (to(b) - to(a)) / (from(b) - from(a))
+            }
+            (None, Some(marker)) => to(marker) + position - from(marker),
+            (None, None) => position,
+        }
+    }
+
+    /// Verifies that a marker is finite and lies strictly between the markers at the given
+    /// indices, which may not exist.
+    fn validate(&self, marker: WarpMarker, prev: Option<usize>, next: usize) -> Result<(), Error> {
+        let invalid = Err(Error::InvalidWarpMarker {
+            source: marker.source,
+            target: marker.target,
+        });
+        if !(marker.source.is_finite() && marker.target.is_finite()) {
+            return invalid;
+        }
+        if let Some(prev) = prev.map(|index| self.markers[index]) {
+            if prev.source >= marker.source || prev.target >= marker.target {
+                return invalid;
+            }
+        }
+        if let Some(next) = self.markers.get(next) {
+            if next.source <= marker.source || next.target <= marker.target {
+                return invalid;
+            }
+        }
+        Ok(())
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+/// Plays back planar input audio along a [`WarpMap`] with the [`Stretcher`], rendering output
+/// blocks of any size.
+///
+/// Grain positions follow the warp map in output (target) time, so playback position and
+/// seeks are target frame positions. The warp map and pitch may be changed while playing:
+/// changes apply with the next grain. Playback finishes when reaching the end of the input.
+///
+/// ```rust, no_run
+/// use bungee_rs::warp::{WarpMap, WarpPlayer};
+///
+/// let input = vec![vec![0.0f32; 44100]; 2];
+/// let mut warp = WarpMap::new();
+/// warp.insert(0.0, 0.0)?;
+/// warp.insert(22050.0, 11025.0)?; // first half at double speed
+/// warp.insert(44100.0, 44100.0)?; // second half at 2/3 speed
+///
+/// let mut player = WarpPlayer::new(input, 44100, warp)?;
+/// player.seek(20000.0)?;
+///
+/// let mut output = vec![vec![0.0f32; 512]; 2];
+/// while !player.is_finished() {
+///     player.process(&mut output);
+///     // use output...
+///     # break;
+/// }
+/// # Ok::<(), bungee_rs::Error>(())
+/// ```
+pub struct WarpPlayer<S = Vec<Vec<f32>>> {
+    playback: Playback<S>,
+    request: Request,
+    warp: WarpMap,
+    pitch: f64,
+    /// Target position of the current grain's centre.
+    output_time: f64,
+    restart: Option<f64>,
+    started: bool,
+}
+
+impl<S: AudioSource> WarpPlayer<S> {
+    /// Creates a new player for the given audio source, which starts playing at target
+    /// position 0 with the original pitch.
+    ///
+    /// # Errors
+    /// Returns an error if the stretcher cannot be created.
+    pub fn new(source: S, sample_rate: usize, warp: WarpMap) -> Result<Self, Error> {
+        Ok(Self {
+            playback: Playback::new(source, sample_rate)?,
+            request: IDLE_REQUEST,
+            warp,
+            pitch: 1.0,
+            output_time: 0.0,
+            restart: None,
+            started: false,
+        })
+    }
+
+    /// Returns the player's sample rate.
+    pub fn sample_rate(&self) -> usize {
+        self.playback.sample_rate()
+    }
+
+    /// Returns the player's channel count.
+    pub fn num_channels(&self) -> usize {
+        self.playback.num_channels()
+    }
+
+    /// Returns the player's audio source.
+    pub fn source(&self) -> &S {
+        self.playback.source()
+    }
+
+    /// Returns the number of frames in the input audio.
+    pub fn input_len(&self) -> usize {
+        self.playback.input_len()
+    }
+
+    /// Returns the target position at which the input audio ends.
+    pub fn target_len(&self) -> f64 {
+        self.warp.target_for(self.input_len() as f64)
+    }
+
+    /// Returns the player's warp map.
+    pub fn warp_map(&self) -> &WarpMap {
+        &self.warp
+    }
+
+    /// Replaces the warp map. Playback continues at the current target position, and jumps to
+    /// the new source position with the next grain.
+    pub fn set_warp_map(&mut self, warp: WarpMap) {
+        self.warp = warp;
+    }
+
+    /// Returns the current pitch shift.
+    pub fn pitch(&self) -> f64 {
+        self.pitch
+    }
+
+    /// Sets the pitch shift (see Request::pitch).
+    ///
+    /// # Errors
+    /// Returns an error if the pitch is not positive and finite.
+    pub fn set_pitch(&mut self, pitch: f64) -> Result<(), Error> {
+        if !(pitch.is_finite() && pitch > 0.0) {
+            return Err(Error::InvalidPitch(pitch));
+        }
+        self.pitch = pitch;
+        Ok(())
+    }
+
+    /// Target frame position of the most recently rendered output frame.
+    pub fn position(&self) -> f64 {
+        self.warp.target_for(self.playback.position())
+    }
+
+    /// Input frame position of the most recently rendered output frame.
+    pub fn source_position(&self) -> f64 {
+        self.playback.position()
+    }
+
+    /// Jumps to the given target frame position. Pending output is discarded, and the
+    /// stretcher restarts on the new position with the next `process` call.
+    ///
+    /// # Errors
+    /// Returns an error if the position is not finite.
+    pub fn seek(&mut self, position: f64) -> Result<(), Error> {
+        if !position.is_finite() {
+            return Err(Error::NonFinitePosition(position));
+        }
+        self.restart = Some(position);
+        self.playback.discard_output(self.warp.source_for(position));
+        Ok(())
+    }
+
+    /// Returns true when playback reached the end of the input and all remaining output
+    /// frames got rendered.
+    pub fn is_finished(&self) -> bool {
+        self.playback.is_finished()
+    }
+
+    /// Renders the next block of output audio. The block size is the length of the first
+    /// output channel. Returns the number of rendered frames, which is less than the block
+    /// size only when the playback finished: remaining frames are filled with silence.
+    ///
+    /// # Panics
+    /// Panics if the output buffers are invalid. See [`Self::try_process`].
+    pub fn process<O: AsMut<[f32]>>(&mut self, output: &mut [O]) -> usize {
+        self.try_process(output)
+            .unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Renders the next block of output audio, just like [`Self::process`], but returns an
+    /// error instead of panicking when the output buffers are invalid.
+    ///
+    /// # Errors
+    /// Returns an error if the number of output channels doesn't match the player's channel
+    /// count or if an output channel is shorter than the first one.
+    pub fn try_process<O: AsMut<[f32]>>(&mut self, output: &mut [O]) -> Result<usize, Error> {
+        let frame_count = self.playback.block_frame_count(output)?;
+        let mut rendered = self.playback.pop(output, 0..frame_count);
+        while rendered < frame_count && self.playback.needs_grain() {
+            self.render_grain();
+            rendered += self.playback.pop(output, rendered..frame_count);
+        }
+        fill_silence(output, rendered..frame_count);
+        Ok(rendered)
+    }
+
+    /// (Re)starts playback at the given target position: the stretcher forgets all previous
+    /// grains and runs in until the output reaches the position.
+    fn start(&mut self, target: f64) {
+        let position = self.warp.source_for(target);
+        self.request = Request {
+            position,
+            speed: self.warp.speed_at(target),
+            pitch: self.pitch,
+            reset: true,
+        };
+        let bounds = f64::NEG_INFINITY..self.input_len() as f64;
+        self.playback.start(&mut self.request, 1.0, Some(bounds));
+        self.output_time = target + (self.request.position - position) / self.request.speed;
+        self.started = true;
+    }
+
+    /// Runs the stretcher's grain loop for a single grain.
+    fn render_grain(&mut self) {
+        if !self.started && self.restart.is_none() {
+            self.restart = Some(0.0);
+        }
+        if let Some(target) = self.restart.take() {
+            self.start(target);
+        }
+
+        // each grain advances the output by exactly one synthesis hop
+        let (position, speed) = (self.request.position, self.request.speed);
+        self.playback.render_grain(&mut self.request);
+        self.output_time += (self.request.position - position) / speed;
+
+        let next_position = self.warp.source_for(self.output_time);
+        // jumps which exceed a grain, e.g. after changing the warp map, are discontinuities
+        self.request.reset = (next_position - self.request.position).abs()
+            > self.playback.max_input_frame_count() as f64;
+        self.request.position = next_position;
+        self.request.speed = self.warp.speed_at(self.output_time);
+        self.request.pitch = self.pitch;
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_signals::{sine, SAMPLE_RATE};
+
+    fn marker(source: f64, target: f64) -> WarpMarker {
+        WarpMarker { source, target }
+    }
+
+    #[test]
+    fn warp_map() {
+        // identity and shift
+        let mut warp = WarpMap::new();
+        assert!(warp.is_empty());
+        assert_eq!(warp.target_for(123.0), 123.0);
+        assert_eq!(warp.speed_at(0.0), 1.0);
+        warp.insert(100.0, 200.0).unwrap();
+        assert_eq!(warp.target_for(0.0), 100.0);
+        assert_eq!(warp.source_for(300.0), 200.0);
+
+        // insertion keeps markers sorted
+        assert_eq!(warp.insert(1100.0, 700.0).unwrap(), 1);
+        assert_eq!(warp.insert(0.0, 0.0).unwrap(), 0);
+        assert_eq!(
+            warp.markers(),
+            &[
+                marker(0.0, 0.0),
+                marker(100.0, 200.0),
+                marker(1100.0, 700.0)
+            ]
+        );
+
+        // lookups interpolate within and extrapolate outside of the markers
+        assert_eq!(warp.target_for(50.0), 100.0);
+        assert_eq!(warp.target_for(600.0), 450.0);
+        assert_eq!(warp.target_for(2100.0), 1200.0);
+        assert_eq!(warp.target_for(-100.0), -200.0);
+        for source in [-100.0, 0.0, 50.0, 600.0, 2100.0] {
+            assert!((warp.source_for(warp.target_for(source)) - source).abs() < 1e-9);
+        }
+        assert_eq!(warp.speed_at(100.0), 0.5);
+        assert_eq!(warp.speed_at(450.0), 2.0);
+        assert_eq!(warp.speed_at(5000.0), 2.0);
+
+        // monotonicity
+        for (source, target) in [
+            (50.0, 300.0),
+            (100.0, 250.0),
+            (2000.0, 700.0),
+            (f64::NAN, 1.0),
+        ] {
+            assert!(matches!(
+                warp.insert(source, target),
+                Err(Error::InvalidWarpMarker { .. })
+            ));
+        }
+        assert!(warp.move_marker(1, 1100.0, 200.0).is_err());
+        warp.move_marker(1, 500.0, 300.0).unwrap();
+        assert_eq!(warp.target_for(500.0), 300.0);
+        assert_eq!(warp.remove(1), marker(500.0, 300.0));
+        assert_eq!(warp.len(), 2);
+        assert!(WarpMap::from_markers([marker(10.0, 10.0), marker(0.0, 20.0)]).is_err());
+        assert_eq!(
+            WarpMap::from_markers(warp.markers().iter().rev().copied()).unwrap(),
+            warp
+        );
+        warp.clear();
+        assert_eq!(warp, WarpMap::new());
+    }
+
+    #[test]
+    fn warp_player() {
+        let input = sine(440.0, 1.0, SAMPLE_RATE);
+        let warp = WarpMap::from_markers([
+            marker(0.0, 0.0),
+            marker(22050.0, 11025.0),
+            marker(44100.0, 44100.0),
+        ])
+        .unwrap();
+
+        let mut player = WarpPlayer::new(vec![input], SAMPLE_RATE, warp.clone()).unwrap();
+        assert_eq!(player.target_len(), 44100.0);
+
+        // seeks are in target time
+        let mut output = vec![vec![0.0f32; 512]];
+        player.seek(11025.0).unwrap();
+        assert!((player.position() - 11025.0).abs() < 1e-6);
+        assert!((player.source_position() - 22050.0).abs() < 1e-6);
+        assert_eq!(player.process(&mut output), 512);
+        assert!((player.position() - 11537.0).abs() < 2.0);
+        for _ in 0..20 {
+            player.process(&mut output);
+        }
+        assert!((player.position() - 11537.0 - 20.0 * 512.0).abs() < 64.0);
+
+        // warp map changes apply while playing
+        player.set_warp_map(WarpMap::new());
+        for _ in 0..20 {
+            player.process(&mut output);
+        }
+        assert!((player.position() - 11537.0 - 40.0 * 512.0).abs() < 64.0);
+
+        // plays through to the end of the warp map, also after seeking backwards
+        player.set_warp_map(warp);
+        player.seek(0.0).unwrap();
+        let mut rendered = 0;
+        for _ in 0..1000 {
+            rendered += player.process(&mut output);
+            assert!(output[0].iter().all(|s| s.is_finite()));
+            if player.is_finished() {
+                break;
+            }
+        }
+        assert!(player.is_finished());
+        assert!(rendered.abs_diff(44100) < 64);
+
+        assert!(player.seek(f64::NAN).is_err());
+    }
+}